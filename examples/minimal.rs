@@ -1,5 +1,4 @@
 //! Testing the file manager menu
-use file_manager;
 
 
 
@@ -12,8 +11,12 @@ fn main() {
         "hello/".to_string(),
         "world/".to_string()
     ]));
-    if let Some(s) = file_manager::run_file_naming_menu(true, dir_vec) {
+    let config = file_manager::FileManagerConfig::default();
+    if let Some(s) = file_manager::run_file_menu(file_manager::MenuMode::Save, dir_vec.clone()) {
         println!("Selected file name: {}", s);
-        file_manager::create_test_file(s);
+        match file_manager::create_test_file_checked(&s, dir_vec.as_deref(), &config) {
+            Ok(_) => println!("File created!"),
+            Err(e) => println!("Failed to create file {e}"),
+        }
     }
 }
@@ -0,0 +1,742 @@
+//! File Manager configuration.
+//! The configuration can be created directly from `FileManagerConfig::default()`, or through the
+//! `FileManagerConfigBuilder`, which validates the configuration when it is built.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use std::{fs, result, fmt};
+
+use thiserror::Error;
+
+use crate::sequential::SEQUENTIAL_NAMING_CHAR;
+use crate::{ConflictAction, EnglishMessages, Messages, MAX_FILE_NAME_CHARS};
+
+// --------------------------------------------------------------------------------
+// Definitions
+// --------------------------------------------------------------------------------
+
+const DEFAULT_MAP_TYPE: &str = "map"; // Do not add the period for the extension.
+
+/// Deleted files are moved here instead of being removed, relative to the execution path.
+const DEFAULT_TRASH_DIRECTORY: &str = "./.trash/";
+
+/// State kept between runs (e.g. the last used directory), stored in the home dir. When there is
+/// no home dir, it is relative to the execution path.
+const DEFAULT_STATE_FILE: &str = ".file_manager_state";
+
+const SEQUENTIAL_FILE_PADDING_LEN: usize = 3;
+const SEQUENTIAL_FILE_MAX_NUMBER: u16 = 999; // Note that the number of digits should fit in the
+                                             // padding length.
+const PRINT_COLUMNS: usize = 4;
+const DEFAULT_PROMPT: &str = "> ";
+const PEEK_BYTES: usize = 256;
+const EMPTY_FILES_NOTE: &str = "(Empty directory)";
+const EMPTY_DIRS_NOTE: &str = "(No directories)";
+const NAME_TEMPLATE: &str = "{name}";
+const HISTORY_SIZE: usize = 100; // Default max_history_size of rustyline.
+
+/// Settings of a dir, merged over the configuration when the menu enters it (see dir_overrides).
+/// It has a subset of TOML: one `key = value` per line and '#' comments, e.g. `extension = "map"`
+/// or `sequential_padding_len = 4`.
+pub const DIR_CONFIG_FILE: &str = ".file_manager.toml";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// The padding length cannot represent the maximum sequential number.
+    PaddingTooShort,
+    /// The listing needs at least one column.
+    NoPrintColumns,
+    /// The file names need at least one character.
+    NoNameChars,
+    /// The extension is empty.
+    EmptyExtension,
+    /// The extension contains a period.
+    ExtensionWithPeriod,
+    /// The name template has no {name}, {counter} is not at its end, or it has a '.' or a '/'.
+    InvalidNameTemplate,
+    /// The sequential separator is a digit, a '.' or a '/'.
+    InvalidSequentialSeparator,
+    /// A line of a dir config file is not a known setting with a valid value.
+    InvalidDirSetting(usize),
+}
+
+impl fmt::Display for ConfigError {
+    /// The English message, see Messages::config_error to print it in another language.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&EnglishMessages.config_error(self))
+    }
+}
+
+/// Source of the current time, used for timestamp based names.
+/// It can be replaced in the configuration to get deterministic names (e.g. in tests).
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// Clock returning the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock always returning the same instant.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub SystemTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+/// Callback invoked by the menu on an event, e.g. `Hook<Path>` gets the path of the event.
+/// It can be cloned with the configuration, all the clones call the same closure.
+pub struct Hook<A: ?Sized>(Arc<Mutex<HookFn<A>>>);
+
+type HookFn<A> = dyn FnMut(&A) + Send;
+
+impl<A: ?Sized> Hook<A> {
+    pub fn new(f: impl FnMut(&A) + Send + 'static) -> Self {
+        Hook(Arc::new(Mutex::new(f)))
+    }
+
+    /// Invokes the callback. It is skipped if a previous call panicked.
+    pub fn call(&self, arg: &A) {
+        if let Ok(mut f) = self.0.lock() {
+            f(arg);
+        }
+    }
+}
+
+impl<A: ?Sized> Clone for Hook<A> {
+    fn clone(&self) -> Self {
+        Hook(Arc::clone(&self.0))
+    }
+}
+
+impl<A: ?Sized> fmt::Debug for Hook<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hook")
+    }
+}
+
+/// Sequential naming modes, i.e. what replaces the trailing '_' of a sequential name.
+/// Timestamps are in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SequentialMode {
+    /// Incremental counter, e.g. "test_001.map".
+    #[default]
+    Counter,
+    /// Timestamp, e.g. "test_20240131_235959.map".
+    Timestamp,
+    /// Incremental counter followed by a timestamp, e.g. "test_001_20240131_235959.map".
+    CounterAndTimestamp,
+}
+
+/// Sort order of the file listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Alphabetical order.
+    #[default]
+    NameAsc,
+    /// Reverse alphabetical order.
+    NameDesc,
+    /// Newest modification time first.
+    MtimeNewest,
+    /// Oldest modification time first.
+    MtimeOldest,
+    /// Largest file first.
+    SizeDesc,
+}
+
+/// Layout of the menu listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListingLayout {
+    /// Default dirs, sub dirs and files in their own sections.
+    #[default]
+    Sectioned,
+    /// Default dirs, sub dirs and files in a single list, the dirs first (ending with '/').
+    Merged,
+}
+
+/// File manager configuration.
+/// Use `FileManagerConfig::default()` to keep the default behaviour, or
+/// `FileManagerConfig::builder()` to get a validated configuration.
+#[derive(Debug, Clone)]
+pub struct FileManagerConfig {
+    /// Directory used to resolve the relative default dirs and typed paths. If empty (default),
+    /// they are relative to the execution path.
+    pub root: PathBuf,
+    /// If not empty, navigation is restricted to these directories and their subdirectories.
+    /// Relative roots are resolved from root.
+    pub allowed_roots: Vec<PathBuf>,
+    /// Directory where deleted files are moved to. The path is used as is (no '~' expansion).
+    pub trash_dir: PathBuf,
+    /// If true, the default dirs that do not exist are created when the menu starts (unless
+    /// read_only is set).
+    pub create_missing_defaults: bool,
+    /// If true, the menu starts in the last used directory (when it still exists).
+    pub remember_last_dir: bool,
+    /// File where the last used directory is stored.
+    pub state_file: PathBuf,
+    /// If true, the first save of the process into a dir creates a run_YYYYMMDD_HHMMSS/ dir in it
+    /// once the save is selected, and the later saves into the dir go there. The save menus start
+    /// in the session dir of the default dir.
+    pub auto_session_dir: bool,
+    /// If true, the settings of the DIR_CONFIG_FILE of a dir are merged over the configuration
+    /// while the menu is in it, e.g. for a dir whose series use 4-digit counters.
+    pub dir_overrides: bool,
+    /// Suffix used for sequential names.
+    pub sequential_mode: SequentialMode,
+    /// Clock used for timestamps.
+    pub clock: Arc<dyn Clock>,
+    /// Extension of the managed files, without the period.
+    pub extension: String,
+    /// Other extensions accepted in typed names, without the period. They are not listed.
+    pub allowed_extra_extensions: Vec<String>,
+    /// Extensions of the companion files that share the name of a managed file, e.g. "meta" for
+    /// "forest_007.meta". They follow the file when it is replaced, renamed or deleted.
+    pub sidecar_extensions: Vec<String>,
+    /// Template applied to the typed names, e.g. "proj42_{name}". {name} is the typed name, and
+    /// an optional {counter} at the end makes every name sequential, e.g. "{name}_{counter}".
+    pub name_template: String,
+    /// If true, files without extension are also listed and selectable when loading.
+    pub include_extensionless: bool,
+    /// If false, typed names without extension are kept as they are instead of getting the
+    /// extension, and files without extension are listed in both modes.
+    pub require_extension: bool,
+    /// If true, loading a file that does not exist returns its path instead of asking for a new
+    /// name.
+    pub allow_load_missing: bool,
+    /// If set, saving into a dir whose files add up to more bytes has to be confirmed.
+    pub dir_size_warn_bytes: Option<u64>,
+    /// Character between the base name and the sequential suffix, e.g. '-' for "test-004.map".
+    pub sequential_separator: char,
+    /// If true, the sequential counters are zero padded to sequential_padding_len; otherwise they
+    /// have only their digits, e.g. "test-4.map".
+    pub sequential_padding: bool,
+    /// Number of digits of the sequential counters (zero padded).
+    pub sequential_padding_len: usize,
+    /// Largest sequential counter.
+    pub sequential_max_number: u16,
+    /// If true, names whose base name differs only by case belong to the same series, as in
+    /// case-insensitive file systems.
+    pub case_insensitive_series: bool,
+    /// If true, once the max counter is reached the lowest free counter of the series is reused.
+    pub reuse_free_counters: bool,
+    /// If true, sequential names selected to be saved are claimed creating the (empty) file, so
+    /// that concurrent saves cannot get the same name.
+    pub claim_sequential_names: bool,
+    /// Sort order of the file listings.
+    pub sort_order: SortOrder,
+    /// Layout of the menu listing.
+    pub listing_layout: ListingLayout,
+    /// Maximum number of files listed in the menu, the rest are only counted.
+    pub max_listed: usize,
+    /// Number of most recently modified files listed in a "Recent" section when loading, 0 for
+    /// none.
+    pub recent_count: usize,
+    /// Number of columns of the listings.
+    pub print_columns: usize,
+    /// Max number of characters of a file name, including the extension. Longer names are
+    /// rejected when saving.
+    pub max_name_chars: usize,
+    /// Width of the listing numbers, right aligned.
+    pub number_width: usize,
+    /// Width of the listed names, left aligned. Longer names are not truncated.
+    pub name_width: usize,
+    /// If false, the files are listed without the configured extension, e.g. "forest_007".
+    pub show_extensions: bool,
+    /// Note printed in place of the files of an empty dir.
+    pub empty_files_note: String,
+    /// Note printed in place of the default dirs or sub dirs when there are none.
+    pub empty_dirs_note: String,
+    /// If true, nothing is modified on disk: no renames, deletions nor directory creation.
+    pub read_only: bool,
+    /// If true, turning an existing file into sequential naming (the 'm' conflict action) shows the
+    /// rename and asks for confirmation first.
+    pub confirm_move_existing: bool,
+    /// If set, it is applied when the name to be saved already exists, without asking for the
+    /// conflict action (nor confirming the move of the existing file), e.g. for automation. It is
+    /// also the action of save_map when none is given.
+    pub conflict_policy: Option<ConflictAction>,
+    /// If true, the final path of the selected file is shown and has to be confirmed, once the
+    /// conflict actions, sequential naming and the other checks are resolved.
+    pub confirm_final: bool,
+    /// If true, the Up/Down keys move a cursor through the numbered listing instead of the history.
+    pub arrow_selection: bool,
+    /// If false, the instructions are not printed with every listing, only with the help command.
+    pub show_help_banner: bool,
+    /// If false, the "Getting sequential name..." message is not printed when a sequential name is
+    /// resolved, e.g. when saving many series files in a loop.
+    pub verbose_sequential: bool,
+    /// Number of bytes printed by the peek command.
+    pub peek_bytes: usize,
+    /// Prompt of the interactive menus.
+    pub prompt: String,
+    /// Texts printed by the menu, e.g. to translate it.
+    pub messages: Arc<dyn Messages>,
+    /// Names added to the input history of the listing, e.g. recent projects or templates.
+    pub extra_history: Vec<String>,
+    /// Maximum number of distinct names typed in the listings kept in the input history of the
+    /// process, e.g. for CTRL+R. 0 keeps none.
+    pub history_size: usize,
+    /// If set, the interactive menu is terminated after this time without input.
+    pub input_timeout: Option<Duration>,
+    /// Called every time the menu moves into a different dir, with the new dir.
+    pub on_dir_change: Option<Hook<Path>>,
+    /// Called when the save menu selects a path, with the path, before it is returned.
+    pub on_save: Option<Hook<Path>>,
+    /// Called when the load menu selects a path, with the path, before it is returned.
+    pub on_load: Option<Hook<Path>>,
+}
+
+impl Default for FileManagerConfig {
+    fn default() -> Self {
+        FileManagerConfig {
+            root: PathBuf::new(),
+            allowed_roots: Vec::new(),
+            trash_dir: PathBuf::from(DEFAULT_TRASH_DIRECTORY),
+            create_missing_defaults: false,
+            remember_last_dir: false,
+            state_file: default_state_file(),
+            auto_session_dir: false,
+            dir_overrides: false,
+            sequential_mode: SequentialMode::default(),
+            clock: Arc::new(SystemClock),
+            extension: DEFAULT_MAP_TYPE.to_string(),
+            allowed_extra_extensions: Vec::new(),
+            sidecar_extensions: Vec::new(),
+            name_template: NAME_TEMPLATE.to_string(),
+            include_extensionless: false,
+            require_extension: true,
+            allow_load_missing: false,
+            dir_size_warn_bytes: None,
+            sequential_separator: SEQUENTIAL_NAMING_CHAR,
+            sequential_padding: true,
+            sequential_padding_len: SEQUENTIAL_FILE_PADDING_LEN,
+            sequential_max_number: SEQUENTIAL_FILE_MAX_NUMBER,
+            case_insensitive_series: false,
+            reuse_free_counters: false,
+            claim_sequential_names: false,
+            sort_order: SortOrder::default(),
+            listing_layout: ListingLayout::default(),
+            max_listed: usize::MAX,
+            recent_count: 0,
+            print_columns: PRINT_COLUMNS,
+            number_width: SEQUENTIAL_FILE_MAX_NUMBER.to_string().len(),
+            name_width: MAX_FILE_NAME_CHARS,
+            max_name_chars: MAX_FILE_NAME_CHARS,
+            show_extensions: true,
+            empty_files_note: EMPTY_FILES_NOTE.to_string(),
+            empty_dirs_note: EMPTY_DIRS_NOTE.to_string(),
+            read_only: false,
+            confirm_move_existing: true,
+            conflict_policy: None,
+            confirm_final: false,
+            arrow_selection: false,
+            show_help_banner: true,
+            verbose_sequential: true,
+            peek_bytes: PEEK_BYTES,
+            prompt: DEFAULT_PROMPT.to_string(),
+            messages: Arc::new(EnglishMessages),
+            extra_history: Vec::new(),
+            history_size: HISTORY_SIZE,
+            input_timeout: None,
+            on_dir_change: None,
+            on_save: None,
+            on_load: None,
+        }
+    }
+}
+
+/// Gets the default state file, in a user writable location instead of next to the maps, so that
+/// read-only dirs can be browsed.
+fn default_state_file() -> PathBuf {
+    match std::env::var_os("HOME").filter(|home| !home.is_empty()) {
+        Some(home) => PathBuf::from(home).join(DEFAULT_STATE_FILE),
+        None => Path::new(".").join(DEFAULT_STATE_FILE),
+    }
+}
+
+// --------------------------------------------------------------------------------
+// Implementations
+// --------------------------------------------------------------------------------
+
+impl FileManagerConfig {
+    /// Gets a builder initialized with the default configuration.
+    pub fn builder() -> FileManagerConfigBuilder {
+        FileManagerConfigBuilder::new()
+    }
+
+    /// Checks the configuration invariants.
+    pub fn validate(&self) -> result::Result<(), ConfigError> {
+        if self.sequential_padding &&
+            self.sequential_max_number.to_string().len() > self.sequential_padding_len {
+            Err(ConfigError::PaddingTooShort)
+        } else if self.sequential_separator.is_ascii_digit() ||
+            matches!(self.sequential_separator, '.' | '/') {
+            Err(ConfigError::InvalidSequentialSeparator)
+        } else if self.print_columns == 0 {
+            Err(ConfigError::NoPrintColumns)
+        } else if self.max_name_chars == 0 {
+            Err(ConfigError::NoNameChars)
+        } else if self.extension.is_empty() ||
+            self.allowed_extra_extensions.iter().chain(&self.sidecar_extensions)
+                .any(String::is_empty) {
+            Err(ConfigError::EmptyExtension)
+        } else if self.extension.contains('.') ||
+            self.allowed_extra_extensions.iter().chain(&self.sidecar_extensions)
+                .any(|extra| extra.contains('.')) {
+            Err(ConfigError::ExtensionWithPeriod)
+        } else if !self.name_template.contains("{name}") ||
+            self.name_template.trim_end_matches("{counter}").contains("{counter}") ||
+            self.name_template.contains(['.', '/']) {
+            Err(ConfigError::InvalidNameTemplate)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Gets the configuration of a dir: this one with the settings of the DIR_CONFIG_FILE of the
+    /// dir merged over it. Only the naming settings can be overridden: extension,
+    /// sequential_mode, sequential_separator, sequential_padding, sequential_padding_len and
+    /// sequential_max_number.
+    ///
+    /// \param dir: directory of the config file.
+    /// \return: the merged configuration, None if the dir has no config file. An error if the file
+    ///          cannot be read, a line is not a valid setting or the merged configuration is
+    ///          invalid.
+    ///
+    pub fn with_dir_overrides(&self, dir: &Path) -> crate::Result<Option<FileManagerConfig>> {
+        let path: PathBuf = dir.join(DIR_CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text: String = fs::read_to_string(path)?;
+        let mut config: FileManagerConfig = self.clone();
+        for (num, line) in text.lines().enumerate() {
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            line.split_once('=')
+                .and_then(|(key, value)| config.set_dir_setting(key.trim(), value.trim()))
+                .ok_or(ConfigError::InvalidDirSetting(num + 1))?;
+        }
+        config.validate()?;
+        Ok(Some(config))
+    }
+
+    /// Sets a setting of a dir config file, the strings are quoted as in TOML.
+    ///
+    /// Returns: None if the key is unknown or the value is not valid for it.
+    fn set_dir_setting(&mut self, key: &str, value: &str) -> Option<()> {
+        let text: Option<&str> = value.strip_prefix('"').and_then(|value| value.strip_suffix('"'));
+        match key {
+            "extension" => self.extension = text?.to_string(),
+            "sequential_mode" => self.sequential_mode = match text? {
+                "counter" => SequentialMode::Counter,
+                "timestamp" => SequentialMode::Timestamp,
+                "counter_and_timestamp" => SequentialMode::CounterAndTimestamp,
+                _ => return None,
+            },
+            "sequential_separator" => {
+                let mut chars = text?.chars();
+                self.sequential_separator = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+            },
+            "sequential_padding" => self.sequential_padding = value.parse().ok()?,
+            "sequential_padding_len" => self.sequential_padding_len = value.parse().ok()?,
+            "sequential_max_number" => self.sequential_max_number = value.parse().ok()?,
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// Builder for `FileManagerConfig`, the configuration is validated on `build()`.
+#[derive(Debug, Clone, Default)]
+pub struct FileManagerConfigBuilder {
+    config: FileManagerConfig,
+}
+
+impl FileManagerConfigBuilder {
+    /// Creates a builder initialized with the default configuration.
+    pub fn new() -> Self {
+        FileManagerConfigBuilder::default()
+    }
+
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.config.root = root.into();
+        self
+    }
+
+    pub fn allowed_roots(mut self, allowed_roots: Vec<PathBuf>) -> Self {
+        self.config.allowed_roots = allowed_roots;
+        self
+    }
+
+    pub fn trash_dir(mut self, trash_dir: impl Into<PathBuf>) -> Self {
+        self.config.trash_dir = trash_dir.into();
+        self
+    }
+
+    pub fn create_missing_defaults(mut self, create_missing_defaults: bool) -> Self {
+        self.config.create_missing_defaults = create_missing_defaults;
+        self
+    }
+
+    pub fn remember_last_dir(mut self, remember_last_dir: bool) -> Self {
+        self.config.remember_last_dir = remember_last_dir;
+        self
+    }
+
+    pub fn state_file(mut self, state_file: impl Into<PathBuf>) -> Self {
+        self.config.state_file = state_file.into();
+        self
+    }
+
+    pub fn auto_session_dir(mut self, auto_session_dir: bool) -> Self {
+        self.config.auto_session_dir = auto_session_dir;
+        self
+    }
+
+    pub fn dir_overrides(mut self, dir_overrides: bool) -> Self {
+        self.config.dir_overrides = dir_overrides;
+        self
+    }
+
+    pub fn sequential_mode(mut self, sequential_mode: SequentialMode) -> Self {
+        self.config.sequential_mode = sequential_mode;
+        self
+    }
+
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.config.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn extension(mut self, extension: impl Into<String>) -> Self {
+        self.config.extension = extension.into();
+        self
+    }
+
+    pub fn allowed_extra_extensions(mut self, allowed_extra_extensions: Vec<String>) -> Self {
+        self.config.allowed_extra_extensions = allowed_extra_extensions;
+        self
+    }
+
+    pub fn sidecar_extensions(mut self, sidecar_extensions: Vec<String>) -> Self {
+        self.config.sidecar_extensions = sidecar_extensions;
+        self
+    }
+
+    pub fn name_template(mut self, name_template: impl Into<String>) -> Self {
+        self.config.name_template = name_template.into();
+        self
+    }
+
+    pub fn include_extensionless(mut self, include_extensionless: bool) -> Self {
+        self.config.include_extensionless = include_extensionless;
+        self
+    }
+
+    pub fn require_extension(mut self, require_extension: bool) -> Self {
+        self.config.require_extension = require_extension;
+        self
+    }
+
+    pub fn allow_load_missing(mut self, allow_load_missing: bool) -> Self {
+        self.config.allow_load_missing = allow_load_missing;
+        self
+    }
+
+    pub fn dir_size_warn_bytes(mut self, dir_size_warn_bytes: u64) -> Self {
+        self.config.dir_size_warn_bytes = Some(dir_size_warn_bytes);
+        self
+    }
+
+    pub fn sequential_separator(mut self, sequential_separator: char) -> Self {
+        self.config.sequential_separator = sequential_separator;
+        self
+    }
+
+    pub fn sequential_padding(mut self, sequential_padding: bool) -> Self {
+        self.config.sequential_padding = sequential_padding;
+        self
+    }
+
+    pub fn sequential_padding_len(mut self, sequential_padding_len: usize) -> Self {
+        self.config.sequential_padding_len = sequential_padding_len;
+        self
+    }
+
+    pub fn sequential_max_number(mut self, sequential_max_number: u16) -> Self {
+        self.config.sequential_max_number = sequential_max_number;
+        self
+    }
+
+    pub fn case_insensitive_series(mut self, case_insensitive_series: bool) -> Self {
+        self.config.case_insensitive_series = case_insensitive_series;
+        self
+    }
+
+    pub fn reuse_free_counters(mut self, reuse_free_counters: bool) -> Self {
+        self.config.reuse_free_counters = reuse_free_counters;
+        self
+    }
+
+    pub fn claim_sequential_names(mut self, claim_sequential_names: bool) -> Self {
+        self.config.claim_sequential_names = claim_sequential_names;
+        self
+    }
+
+    pub fn sort_order(mut self, sort_order: SortOrder) -> Self {
+        self.config.sort_order = sort_order;
+        self
+    }
+
+    pub fn listing_layout(mut self, listing_layout: ListingLayout) -> Self {
+        self.config.listing_layout = listing_layout;
+        self
+    }
+
+    pub fn max_listed(mut self, max_listed: usize) -> Self {
+        self.config.max_listed = max_listed;
+        self
+    }
+
+    pub fn recent_count(mut self, recent_count: usize) -> Self {
+        self.config.recent_count = recent_count;
+        self
+    }
+
+    pub fn print_columns(mut self, print_columns: usize) -> Self {
+        self.config.print_columns = print_columns;
+        self
+    }
+
+    pub fn number_width(mut self, number_width: usize) -> Self {
+        self.config.number_width = number_width;
+        self
+    }
+
+    pub fn name_width(mut self, name_width: usize) -> Self {
+        self.config.name_width = name_width;
+        self
+    }
+
+    pub fn max_name_chars(mut self, max_name_chars: usize) -> Self {
+        self.config.max_name_chars = max_name_chars;
+        self
+    }
+
+    pub fn show_extensions(mut self, show_extensions: bool) -> Self {
+        self.config.show_extensions = show_extensions;
+        self
+    }
+
+    pub fn empty_files_note(mut self, empty_files_note: impl Into<String>) -> Self {
+        self.config.empty_files_note = empty_files_note.into();
+        self
+    }
+
+    pub fn empty_dirs_note(mut self, empty_dirs_note: impl Into<String>) -> Self {
+        self.config.empty_dirs_note = empty_dirs_note.into();
+        self
+    }
+
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.config.read_only = read_only;
+        self
+    }
+
+    pub fn confirm_move_existing(mut self, confirm_move_existing: bool) -> Self {
+        self.config.confirm_move_existing = confirm_move_existing;
+        self
+    }
+
+    pub fn conflict_policy(mut self, conflict_policy: ConflictAction) -> Self {
+        self.config.conflict_policy = Some(conflict_policy);
+        self
+    }
+
+    pub fn confirm_final(mut self, confirm_final: bool) -> Self {
+        self.config.confirm_final = confirm_final;
+        self
+    }
+
+    pub fn arrow_selection(mut self, arrow_selection: bool) -> Self {
+        self.config.arrow_selection = arrow_selection;
+        self
+    }
+
+    pub fn show_help_banner(mut self, show_help_banner: bool) -> Self {
+        self.config.show_help_banner = show_help_banner;
+        self
+    }
+
+    pub fn verbose_sequential(mut self, verbose_sequential: bool) -> Self {
+        self.config.verbose_sequential = verbose_sequential;
+        self
+    }
+
+    pub fn peek_bytes(mut self, peek_bytes: usize) -> Self {
+        self.config.peek_bytes = peek_bytes;
+        self
+    }
+
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.config.prompt = prompt.into();
+        self
+    }
+
+    pub fn messages(mut self, messages: impl Messages + 'static) -> Self {
+        self.config.messages = Arc::new(messages);
+        self
+    }
+
+    pub fn extra_history(mut self, extra_history: Vec<String>) -> Self {
+        self.config.extra_history = extra_history;
+        self
+    }
+
+    pub fn history_size(mut self, history_size: usize) -> Self {
+        self.config.history_size = history_size;
+        self
+    }
+
+    pub fn input_timeout(mut self, input_timeout: Duration) -> Self {
+        self.config.input_timeout = Some(input_timeout);
+        self
+    }
+
+    pub fn on_dir_change(mut self, on_dir_change: impl FnMut(&Path) + Send + 'static) -> Self {
+        self.config.on_dir_change = Some(Hook::new(on_dir_change));
+        self
+    }
+
+    pub fn on_save(mut self, on_save: impl FnMut(&Path) + Send + 'static) -> Self {
+        self.config.on_save = Some(Hook::new(on_save));
+        self
+    }
+
+    pub fn on_load(mut self, on_load: impl FnMut(&Path) + Send + 'static) -> Self {
+        self.config.on_load = Some(Hook::new(on_load));
+        self
+    }
+
+    /// Validates and returns the configuration.
+    pub fn build(self) -> result::Result<FileManagerConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
@@ -0,0 +1,553 @@
+//! Interactive menu.
+//! Drives the file name menu with rustyline: line edition, history, arrow selection and tab
+//! completion. Only built with the 'interactive' feature (enabled by default), without it the
+//! crate has no terminal dependencies and the menu can still be driven with `FileNameMenu`.
+
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+#[cfg(unix)]
+use std::time::Instant;
+use rustyline::completion::{Candidate, Completer};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::{DefaultHistory, History};
+use rustyline::validate::Validator;
+use rustyline::{Cmd, ConditionalEventHandler, Context, Editor, Event, EventContext, EventHandler,
+                Helper, KeyCode, KeyEvent, Modifiers, Movement, RepeatCount};
+
+use crate::{fuzzy_match, get_fuzzy_pattern, highlight_chars, save_last_dir, Error,
+            FileManagerConfig, FileNameMenu, ListingEntry, MenuMode, MenuOutcome, MenuState,
+            MenuStep, Result};
+
+// ----------------------------------------
+// Arrow selection
+// ----------------------------------------
+
+/// Cursor over the numbered listing (default dirs followed by files), moved with the Up/Down
+/// keys. Each key press replaces the input with the number of the highlighted entry, so Enter
+/// selects it as if the number had been typed.
+#[derive(Clone)]
+struct ListingCursor {
+    len: usize,
+    position: Arc<Mutex<Option<usize>>>,
+}
+
+impl ConditionalEventHandler for ListingCursor {
+    fn handle(&self, evt: &Event, _: RepeatCount, _: bool, _: &EventContext) -> Option<Cmd> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut position = self.position.lock().ok()?;
+        let next: usize = match (evt.get(0)?.0, *position) {
+            (KeyCode::Up, Some(p)) => p.checked_sub(1).unwrap_or(self.len - 1),
+            (KeyCode::Up, None) => self.len - 1,
+            (KeyCode::Down, Some(p)) => (p + 1) % self.len,
+            (KeyCode::Down, None) => 0,
+            _ => return None,
+        };
+        *position = Some(next);
+        Some(Cmd::Replace(Movement::WholeLine, Some(next.to_string())))
+    }
+}
+
+/// Binds the Up/Down keys to a new cursor over the listing.
+/// Terminals without key support fall back to plain number input.
+fn bind_listing_cursor(rl: &mut Editor<MenuHelper, DefaultHistory>, len: usize) {
+    let cursor = ListingCursor { len, position: Arc::new(Mutex::new(None)) };
+    for code in [KeyCode::Up, KeyCode::Down] {
+        rl.bind_sequence(KeyEvent(code, Modifiers::NONE),
+                         EventHandler::Conditional(Box::new(cursor.clone())));
+    }
+}
+
+/// Completion candidate of the file name menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FileCandidate {
+    /// Command word valid for the current prompt.
+    Command(String),
+    /// Dir, always with a trailing '/' so that the completion can continue inside it.
+    Dir(String),
+    /// File of the current dir.
+    File(String),
+}
+
+impl FileCandidate {
+    fn dir(name: &str) -> Self {
+        FileCandidate::Dir(format!("{}/", name.trim_end_matches('/')))
+    }
+}
+
+impl Candidate for FileCandidate {
+    fn display(&self) -> &str {
+        match self {
+            FileCandidate::Command(text) | FileCandidate::Dir(text) | FileCandidate::File(text) => text,
+        }
+    }
+
+    fn replacement(&self) -> &str {
+        self.display()
+    }
+}
+
+const FUZZY_HINT_ROWS: usize = 10; // Matches listed under the input while typing a pattern.
+
+/// Readline helper of the file name menu.
+/// With arrow selection enabled, a number input is hinted with its highlighted entry.
+/// A fuzzy pattern ("/<text>") narrows the listing while typing: its best matches are listed under
+/// the input and updated on every key press.
+/// Tab completes the command words valid for the current prompt, and the listed dirs and files.
+struct MenuHelper {
+    entries: Vec<String>,
+    arrow_selection: bool,
+    candidates: Vec<FileCandidate>,
+}
+
+impl Hinter for MenuHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _: &Context<'_>) -> Option<String> {
+        if pos != line.len() {
+            return None;
+        }
+        // Live fuzzy filter, the narrowed listing is rendered again on every key press.
+        if let Some(pattern) = get_fuzzy_pattern(line) {
+            let mut matches: Vec<(u32, &String, Vec<usize>)> = self.entries
+                .iter()
+                .filter_map(|entry| fuzzy_match(pattern, entry)
+                    .map(|(score, positions)| (score, entry, positions)))
+                .collect();
+            // Stable sort, ties keep the listing order.
+            matches.sort_by_key(|(score, ..)| Reverse(*score));
+            let Some((_, best, _)) = matches.first() else {
+                return Some("  -> no matches".to_string());
+            };
+            let mut hint: String = format!("  -> {} ({} matches)", best, matches.len());
+            for (_, entry, positions) in matches.iter().take(FUZZY_HINT_ROWS) {
+                hint.push_str(&format!("\n    {}", highlight_chars(entry, positions)));
+            }
+            if matches.len() > FUZZY_HINT_ROWS {
+                hint.push_str("\n    ...");
+            }
+            return Some(hint);
+        }
+        if !self.arrow_selection {
+            return None;
+        }
+        let entry: &String = self.entries.get(line.parse::<usize>().ok()?)?;
+        Some(format!("  <- {}", entry))
+    }
+}
+
+impl Highlighter for MenuHelper {
+    /// Only the first line is shown in reverse video, the narrowed listing keeps its highlights.
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        match hint.split_once('\n') {
+            Some((first, listing)) => Cow::Owned(format!("\x1b[7m{}\x1b[0m\n{}", first, listing)),
+            None => Cow::Owned(format!("\x1b[7m{}\x1b[0m", hint)), // Reverse video.
+        }
+    }
+}
+
+impl Completer for MenuHelper {
+    type Candidate = FileCandidate;
+
+    /// Completes the last word. Commands are only completed as the first word.
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>)
+        -> rustyline::Result<(usize, Vec<FileCandidate>)> {
+        let typed: &str = &line[..pos];
+        let start: usize = typed.rfind(' ').map_or(0, |i| i + 1);
+        let candidates: Vec<FileCandidate> = self.candidates
+            .iter()
+            .filter(|candidate| start == 0 || !matches!(candidate, FileCandidate::Command(_)))
+            .filter(|candidate| candidate.replacement().starts_with(&typed[start..]))
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Validator for MenuHelper {}
+
+impl Helper for MenuHelper {}
+
+// ----------------------------------------
+// Menu driver
+// ----------------------------------------
+
+impl FileNameMenu<'_> {
+    /// Completion candidates for the current prompt: the command words, and in the listing also
+    /// the listed dirs and files.
+    fn completion_candidates(&self) -> Vec<FileCandidate> {
+        let commands = self.commands().into_iter().map(FileCandidate::Command);
+        match self.state {
+            MenuState::Listing => commands
+                .chain(self.numbered.iter().map(|entry| match entry {
+                    ListingEntry::Dir(dir) => FileCandidate::dir(dir),
+                    ListingEntry::File(file) => FileCandidate::File(file.clone()),
+                }))
+                .collect(),
+            _ => commands.collect(),
+        }
+    }
+}
+
+/// Names typed in the listings of the process, oldest first and without duplicates, so that they
+/// can be reached again (e.g. with CTRL+R) after moving to other dirs or in later menus.
+static TYPED_HISTORY: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Adds a typed line to TYPED_HISTORY, keeping the last history_size distinct lines.
+fn add_typed_history(line: &str, config: &FileManagerConfig) {
+    if line.trim().is_empty() {
+        return;
+    }
+    let mut history: MutexGuard<Vec<String>> =
+        TYPED_HISTORY.lock().unwrap_or_else(PoisonError::into_inner);
+    history.retain(|entry| entry != line);
+    history.push(line.to_string());
+    let excess: usize = history.len().saturating_sub(config.history_size);
+    history.drain(..excess);
+}
+
+/// Sets the history of the editor: the typed names (in the listing) followed by the suggestions,
+/// so that the Up key reaches the suggestions first.
+fn set_history(rl: &mut Editor<MenuHelper, DefaultHistory>, suggestions: &[String],
+               listing: bool) -> Result<()> {
+    let typed: Vec<String> = if listing {
+        TYPED_HISTORY.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    } else {
+        Vec::new()
+    };
+    rl.clear_history()?;
+    rl.history_mut().set_max_len(typed.len() + suggestions.len())?;
+    for s in typed.iter().chain(suggestions.iter().rev()) {
+        rl.add_history_entry(s)?;
+    }
+    Ok(())
+}
+
+/// Reads a line, giving up after timeout if set.
+/// Readline cannot be interrupted, so with a timeout the prompt is printed and stdin is polled
+/// first; the line is only read once a key has been pressed. Nothing is left reading stdin on
+/// timeout.
+fn read_line(rl: &mut Editor<MenuHelper, DefaultHistory>, initial: &str,
+             config: &FileManagerConfig) -> Result<rustyline::Result<String>> {
+    if let Some(timeout) = config.input_timeout {
+        print!("{}{}", config.prompt, initial);
+        io::stdout().flush()?;
+        let ready: bool = wait_for_input(timeout)?;
+        // The editor draws the prompt again over this one.
+        print!("\r");
+        if !ready {
+            println!();
+            println!("{}", config.messages.input_timeout(timeout));
+            return Err(Error::ManuallyTerminated);
+        }
+    }
+    Ok(rl.readline_with_initial(&config.prompt, (initial, "")))
+}
+
+/// Waits until stdin has input, for up to timeout. Terminals are switched to non canonical mode
+/// while waiting, so that the first key press counts as input (not only a full line), and they
+/// are restored before returning. Nothing is read, the pending input is read by the editor.
+///
+/// Returns: false if the timeout expired without input.
+#[cfg(unix)]
+fn wait_for_input(timeout: Duration) -> io::Result<bool> {
+    let fd: libc::c_int = libc::STDIN_FILENO;
+    // Restored when dropped, on every return path. None if stdin is not a terminal.
+    let _terminal: Option<NonCanonicalTerminal> = NonCanonicalTerminal::enter(fd);
+    let deadline: Instant = Instant::now() + timeout;
+    loop {
+        let left: Duration = deadline.saturating_duration_since(Instant::now());
+        let millis: libc::c_int = left.as_millis().try_into().unwrap_or(libc::c_int::MAX);
+        let mut poll_fd: libc::pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        // SAFETY: poll_fd is a valid pollfd that outlives the call, and the count is 1 as it is a
+        // single one.
+        match unsafe { libc::poll(&mut poll_fd, 1, millis) } {
+            -1 => {
+                let e: io::Error = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            },
+            ready => return Ok(ready > 0),
+        }
+    }
+}
+
+/// Terminal switched to non canonical mode without echo, its previous settings are restored when
+/// dropped.
+#[cfg(unix)]
+struct NonCanonicalTerminal {
+    fd: libc::c_int,
+    saved: libc::termios,
+}
+
+#[cfg(unix)]
+impl NonCanonicalTerminal {
+    /// Switches a terminal to non canonical mode without echo.
+    ///
+    /// Returns: None if fd is not a terminal, nothing is changed then.
+    fn enter(fd: libc::c_int) -> Option<Self> {
+        // SAFETY: termios is a plain C struct, for which all zero bytes is a valid value, and it
+        // is only used once tcgetattr has filled it.
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        // SAFETY: saved is a valid termios that outlives the call, and an invalid or non terminal
+        // fd only makes the call fail.
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return None;
+        }
+        let mut waiting: libc::termios = saved;
+        waiting.c_lflag &= !(libc::ICANON | libc::ECHO);
+        // SAFETY: waiting is a valid termios, read from this terminal by tcgetattr.
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &waiting) };
+        Some(Self { fd, saved })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for NonCanonicalTerminal {
+    fn drop(&mut self) {
+        // SAFETY: saved is the valid termios read from this terminal by tcgetattr.
+        unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.saved) };
+    }
+}
+
+/// Without a pollable stdin the timeout is not applied, the line is read as usual.
+#[cfg(not(unix))]
+fn wait_for_input(_timeout: Duration) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Drives the file name menu with rustyline until a file is selected or the menu is cancelled.
+fn file_name_menu(menu: &mut FileNameMenu, config: &FileManagerConfig) -> Result<String> {
+    let mut rl: Editor<MenuHelper, DefaultHistory> = Editor::new()?;
+    let mut suggestions: Vec<String> = Vec::new();
+
+    drive_menu(menu, config, |menu, refresh| {
+        let new_suggestions: Vec<String> = menu.suggestions();
+        if refresh || new_suggestions != suggestions {
+            let entries: Vec<String> = menu.entries();
+            if config.arrow_selection {
+                bind_listing_cursor(&mut rl, entries.len());
+            }
+            rl.set_helper(Some(MenuHelper {
+                entries,
+                arrow_selection: config.arrow_selection,
+                candidates: menu.completion_candidates(),
+            }));
+            set_history(&mut rl, &new_suggestions, matches!(menu.state, MenuState::Listing))?;
+            suggestions = new_suggestions;
+        }
+        let line: rustyline::Result<String> = read_line(&mut rl, menu.initial_input(), config)?;
+        if let Ok(line) = &line {
+            rl.add_history_entry(line)?;
+        }
+        Ok(line)
+    })
+}
+
+/// Drives the file name menu with the lines of next_line until a file is selected or the menu is
+/// cancelled.
+///
+/// \param menu: menu to drive.
+/// \param config: file manager configuration.
+/// \param next_line: reads the next line for the menu, with true if it has just been rendered.
+/// \return: the selected file, Error::ManuallyTerminated if the menu is cancelled.
+///
+fn drive_menu(menu: &mut FileNameMenu, config: &FileManagerConfig,
+              mut next_line: impl FnMut(&FileNameMenu, bool) -> Result<rustyline::Result<String>>)
+    -> Result<String> {
+    let mut step: MenuStep = MenuStep::Refresh;
+
+    loop {
+        let refresh: bool = match step {
+            MenuStep::Selected(path) => return Ok(path),
+            MenuStep::Cancelled => return Err(Error::ManuallyTerminated),
+            MenuStep::Failed(e) => return Err(e),
+            MenuStep::Refresh => {
+                menu.render();
+                true
+            },
+            MenuStep::NeedMoreInput => false,
+        };
+        step = match next_line(menu, refresh)? {
+            Ok(line) => {
+                if matches!(menu.state, MenuState::Listing) {
+                    add_typed_history(&line, config);
+                }
+                menu.handle_input(&line)
+            },
+            Err(ReadlineError::Interrupted) => menu.handle_interrupt(), // CTRL+C
+            Err(ReadlineError::Eof) => menu.handle_eof(), // CTRL+D
+            // Any other error is likely to repeat on every read, so the menu is ended.
+            Err(err) => return Err(Error::Cmd(err)),
+        };
+    }
+}
+
+/// Intermediate function to handle errors in the save file menu.
+/// Depending on the mode, it will run the file saving or the load file option.
+///
+fn run_save_file_menu_with_errors(mode: MenuMode, default_dirs: Option<Vec<String>>,
+                                  config: &FileManagerConfig) -> Result<String> {
+    run_menu_with_errors(&mut FileNameMenu::new(mode, default_dirs, config)?, config)
+}
+
+/// Drives the menu and stores the last used dir.
+fn run_menu_with_errors(menu: &mut FileNameMenu, config: &FileManagerConfig) -> Result<String> {
+    let full_path: Result<String> = file_name_menu(menu, config);
+    if config.remember_last_dir {
+        if let Err(e) = save_last_dir(menu.current_dir(), config) {
+            println!("{}", config.messages.error(&e));
+        }
+    }
+    full_path
+}
+
+// ----------------------------------------
+// Mains
+// ----------------------------------------
+
+/// Runs the file menu.
+///
+/// All errors are handled internally for simplicity.
+///
+/// \param mode: serves the file save or the file load menu.
+/// \param default_dirs: default dirs to list, if None the default dir is used.
+/// \return: option with the selected file name, None if error took place or it was canceled.
+///
+pub fn run_file_menu(mode: MenuMode, default_dirs: Option<Vec<String>>) -> Option<String> {
+    run_file_menu_with_config(mode, default_dirs, &FileManagerConfig::default())
+}
+
+/// Runs the file menu with a custom configuration.
+///
+/// \param mode: serves the file save or the file load menu.
+/// \param default_dirs: default dirs to list, if None the default dir is used.
+/// \param config: file manager configuration.
+/// \return: option with the selected file name, None if error took place or it was canceled.
+///
+pub fn run_file_menu_with_config(mode: MenuMode, default_dirs: Option<Vec<String>>,
+                                 config: &FileManagerConfig) -> Option<String> {
+    match run_save_file_menu_with_errors(mode, default_dirs, config) {
+        Err(e) => {
+            println!("{}", config.messages.error(&e));
+            None
+        }
+        Ok(s) => Some(s)
+    }
+}
+
+/// Runs the file menu, keeping the dir the user was in when it is cancelled, e.g. to start there
+/// the next time.
+///
+/// \param mode: serves the file save or the file load menu.
+/// \param default_dirs: default dirs to list, if None the default dir is used.
+/// \param config: file manager configuration.
+/// \return: the selected file with its sequential counter, or the current dir if the menu was
+///          cancelled (CTRL+D or input_timeout). Other errors are returned as they are.
+///
+pub fn run_file_menu_with_outcome(mode: MenuMode, default_dirs: Option<Vec<String>>,
+                                  config: &FileManagerConfig) -> Result<MenuOutcome> {
+    let mut menu: FileNameMenu = FileNameMenu::new(mode, default_dirs, config)?;
+    match run_menu_with_errors(&mut menu, config) {
+        Ok(path) =>
+            Ok(MenuOutcome::Selected { path, sequential_number: menu.sequential_number() }),
+        Err(Error::ManuallyTerminated) =>
+            Ok(MenuOutcome::Cancelled(menu.current_dir().to_string())),
+        Err(e) => Err(e),
+    }
+}
+
+/// Runs the file saving menu, detecting existing files with the same content.
+/// If a file of the selected dir has the same content, it can be selected instead of saving a
+/// duplicate.
+///
+/// \param default_dirs: default dirs to list, if None the default dir is used.
+/// \param content: content to be saved.
+/// \param config: file manager configuration.
+/// \return: option with the selected file name, None if error took place or it was canceled.
+///
+pub fn run_file_saving_menu_with_content(default_dirs: Option<Vec<String>>, content: &[u8],
+                                         config: &FileManagerConfig) -> Option<String> {
+    let result: Result<String> = FileNameMenu::new(MenuMode::Save, default_dirs, config)
+        .and_then(|menu| run_menu_with_errors(&mut menu.with_content(content), config));
+    match result {
+        Err(e) => {
+            println!("{}", config.messages.error(&e));
+            None
+        }
+        Ok(s) => Some(s)
+    }
+}
+
+/// Runs the file naming menu.
+///
+/// \param is_saving: if true serves the file save menu; otherwise it serves the load file menu.
+/// \return: option with the selected file name, None if error took place or it was canceled.
+///
+#[deprecated(note = "use run_file_menu with a MenuMode")]
+pub fn run_file_naming_menu(is_saving: bool, default_dirs: Option<Vec<String>>) -> Option<String> {
+    run_file_menu(MenuMode::from(is_saving), default_dirs)
+}
+
+/// Runs the file naming menu with a custom configuration.
+///
+/// \param is_saving: if true serves the file save menu; otherwise it serves the load file menu.
+/// \param config: file manager configuration.
+/// \return: option with the selected file name, None if error took place or it was canceled.
+///
+#[deprecated(note = "use run_file_menu_with_config with a MenuMode")]
+pub fn run_file_naming_menu_with_config(is_saving: bool, default_dirs: Option<Vec<String>>,
+                                        config: &FileManagerConfig) -> Option<String> {
+    run_file_menu_with_config(MenuMode::from(is_saving), default_dirs, config)
+}
+
+// ----------------------------------------
+// Tests
+// ----------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::tests::{dir_arg, test_dir};
+
+    #[test]
+    fn repeated_readline_errors_end_the_menu() {
+        let dir: PathBuf = test_dir("readline_errors");
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&dir)]), &config).unwrap();
+        let mut reads: usize = 0;
+        let result: Result<String> = drive_menu(&mut menu, &config, |_, _| {
+            reads += 1;
+            assert!(reads < 3, "the menu keeps reading after a readline error");
+            Ok(Err(ReadlineError::Io(io::Error::other("broken terminal"))))
+        });
+        assert!(matches!(result, Err(Error::Cmd(ReadlineError::Io(_)))));
+        assert_eq!(reads, 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn interrupts_restart_the_input_and_eof_cancels() {
+        let dir: PathBuf = test_dir("readline_interrupts");
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&dir)]), &config).unwrap();
+        let mut reads: Vec<ReadlineError> = vec![ReadlineError::Eof, ReadlineError::Interrupted];
+        let result: Result<String> =
+            drive_menu(&mut menu, &config, |_, _| Ok(Err(reads.pop().unwrap())));
+        assert!(matches!(result, Err(Error::ManuallyTerminated)));
+        assert!(reads.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
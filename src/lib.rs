@@ -5,15 +5,38 @@
 //! Version: 0.0 - first version.
 //! Version: 1.0 - Adding support for dir changes.
 
+use std::borrow::Cow;
+use std::collections::{BinaryHeap, HashMap};
+use std::ffi::OsString;
 use std::fs;
 use std::io;
-use std::path::{Path, PathBuf};
-use std::{result, fmt};
-use rustyline::completion::Candidate;
-use rustyline::error::ReadlineError;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::{cmp, result, fmt};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use thiserror::Error;
 
+mod config;
+#[cfg(feature = "interactive")]
+mod interactive;
+mod messages;
+pub mod sequential;
+use sequential::{claim_sequential_name, format_timestamp, get_latest_per_series, get_missing_counters, get_next_sequential_name, get_sequential_base_name,
+                 get_sequential_name_from_count,
+                 current_timestamp, get_renumber_renames, get_sequential_counter,
+                 get_sequential_name_and_count, get_series, is_sequential_name};
+#[cfg(feature = "interactive")]
+#[allow(deprecated)]
+pub use interactive::{run_file_menu, run_file_menu_with_config, run_file_menu_with_outcome,
+                      run_file_naming_menu, run_file_naming_menu_with_config,
+                      run_file_saving_menu_with_content};
+pub use config::{Clock, ConfigError, FileManagerConfig, FileManagerConfigBuilder,
+                 FixedClock, Hook, ListingLayout, SequentialMode, SortOrder, SystemClock,
+                 DIR_CONFIG_FILE};
+pub use messages::{EnglishMessages, Messages};
+
 // --------------------------------------------------------------------------------
 // Definitions
 // --------------------------------------------------------------------------------
@@ -22,20 +45,30 @@ use thiserror::Error;
 /// This will assume that the cargo run is called from the main project dir.
 const DEFAULT_DIRECTORY: &str = "./test_dir/";
 
-const DEFAULT_MAP_TYPE: &str = "map"; // Do not add the period for the extension.
+const MAX_FILE_NAME_CHARS: usize = 30; // Default max_name_chars, and name width of the listings.
+
+const BREADCRUMB_SEPARATOR: &str = " › ";
+
+const LITERAL_NAME_CHAR: char = '='; // Prefix of a typed name that is not a number, e.g. "=2024".
+
+const SESSION_DIR_PREFIX: &str = "run_"; // Followed by the timestamp, e.g. "run_20240131_235959/".
+
+/// Session dirs of auto_session_dir with the dir they are in, created by the first save of the
+/// process into each dir.
+static SESSION_DIRS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+const RENAME_TEMP_SUFFIX: &str = ".renaming"; // Temporary names of rename_all_via_temp.
+// Before the counter of a name already in the trash, e.g. "forest~002.map".
+const TRASH_SUFFIX_CHAR: char = '~';
 
-const SEQUENTIAL_FILE_PADDING_LEN: usize = 3;
-const SEQUENTIAL_NAMING_CHAR: char = '_';
-const SEQUENTIAL_FILE_MAX_NUMBER: u16 = 999; // Note that the number of digits should match the
-                                             // digits defined in print_dir_files and path_from_cnt
-const PRINT_COLUMNS: usize = 4;
-const MAX_FILE_NAME_CHARS: usize = 30; // Note that this should match the print in print_dir_files.
 
 #[derive(Error, Debug)]
-enum Error {
+pub enum Error {
     /// External errors
     Io(#[from] io::Error),
+    #[cfg(feature = "interactive")]
     Cmd(#[from] rustyline::error::ReadlineError),
+    Config(#[from] ConfigError),
 
     /// Manually terminated, not really an error but useful to state no file has been selected.
     ManuallyTerminated,
@@ -47,549 +80,3716 @@ enum Error {
     FileDeletion,
 
     /// Custom errors.
-    InvalidNameTooLong,
-    InvalidSequentialName,
-    UnknownFileType,
+    /// The file name has more characters than max_name_chars, with the limit.
+    InvalidNameTooLong(usize),
+    InvalidNameChars,
+    /// A dir or file is outside every allowed root, with its resolved path and the roots.
+    OutsideRoot { path: PathBuf, roots: Vec<PathBuf> },
+    InvalidSequentialName(u16),
+    UnknownFileType(String),
+    RestoreTargetExists,
+    /// The file to restore is not in the trash, with its name.
+    TrashEntryNotFound(String),
+    SeriesTargetExists(String),
+    ReadOnly,
+    /// A directory could not be created, e.g. because a component of its path is a file.
+    DirCreateFailed { path: PathBuf, source: io::Error },
+    /// The file to be saved already exists and the conflict action is ConflictAction::Fail.
+    FileExists(PathBuf),
 }
 
 impl fmt::Display for Error {
+    /// The English message, see Messages::error to print it in another language.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Error::Io(ref err) => err.fmt(f),
-            Error::Cmd(ref err) => err.fmt(f),
-            Error::ManuallyTerminated =>
-                write!(f, "FILE MNG :: File selection has been manually terminated with CTRL+D"),
-            Error::NeedNewName =>
-                write!(f, "FILE MNG :: File selection needs to be re-run."),
-            Error::FileDeletion =>
-                write!(f, "FILE MNG :: Specified file has been deleted."),
-            Error::InvalidNameTooLong =>
-                write!(f, "FILE MNG :: Error selected file name is longer than {}.",
-                       MAX_FILE_NAME_CHARS),
-            Error::InvalidSequentialName =>
-                write!(f, "FILE MNG :: Error sequential name count larger than {}.",
-                       SEQUENTIAL_FILE_MAX_NUMBER),
-            Error::UnknownFileType =>
-                write!(f, "FILE MNG :: Error unsupported file type, use {}.",
-                       DEFAULT_MAP_TYPE),
-        }
-    }
-}
-
-type Result<T> = result::Result<T, Error>;
+        f.write_str(&EnglishMessages.error(self))
+    }
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// Files listed in the menu.
+struct MenuFileList {
+    /// Sub dirs of the listed dir, ending with '/'. The parent dir ("../") goes first.
+    sub_dirs: Vec<String>,
+    files: Vec<String>,
+    /// Number of files not listed because of max_listed.
+    omitted: usize,
+    /// Note printed when there are no files for another reason than an empty dir (e.g. permission
+    /// denied), instead of the configured empty_files_note.
+    empty_note: Option<String>,
+    /// Filter applied to the files.
+    filter: SeriesFilter,
+    /// Most recently modified files of the dir, newest first (see recent_count), taken from the
+    /// collected files so that the dir is read once. They are also in files, unless filtered.
+    recent: Vec<String>,
+    /// Listed dir.
+    dir: PathBuf,
+    /// Metadata of the collected files (before the filter), read once per listing so that it is
+    /// not read again while the listing is shown, e.g. on network file systems.
+    metadata: HashMap<String, fs::Metadata>,
+    /// Dirs and files that are not listed because their names are not valid UTF-8.
+    skipped: Vec<OsString>,
+}
+
+/// Files collected from a dir, see get_file_entries_limited.
+struct FileEntries {
+    /// Collected files with their metadata (None if it cannot be read), sorted as configured.
+    files: Vec<(String, Option<fs::Metadata>)>,
+    /// Number of files not collected because of the limit.
+    omitted: usize,
+    /// Names skipped because they are not valid UTF-8, for the caller to report them.
+    skipped: Vec<OsString>,
+}
+
+/// Filter of the listed files by sequential series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SeriesFilter {
+    /// All the files.
+    #[default]
+    All,
+    /// Only files of a sequential series.
+    Series,
+    /// Only files that are not part of a sequential series.
+    Single,
+    /// Only the latest file of each series, and the files that are not part of a series.
+    Latest,
+}
+
+/// Numbered entry of the menu listing.
+enum ListingEntry {
+    /// Default dir or sub dir, ending with '/'.
+    Dir(String),
+    /// File of the current dir.
+    File(String),
+}
+
+/// Mode of the file name menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuMode {
+    /// Select the name of a file to be saved.
+    Save,
+    /// Select an existing file to be loaded.
+    Load,
+}
+
+/// Actions to resolve a name conflict, i.e. saving a file that already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictAction {
+    /// Replace the existing file.
+    Replace,
+    /// Turn the existing file into sequential naming, the new file keeps the name.
+    MoveExisting,
+    /// Turn the new file into sequential naming.
+    SequentialNew,
+    /// Select a new name.
+    NewName,
+    /// Delete the existing file, it is moved to the trash.
+    Delete,
+    /// Keep the existing file and fail with Error::FileExists. Not offered by the conflict menu,
+    /// it is meant for conflict_policy and save_map.
+    Fail,
+}
+
+/// Mismatch between the configuration and a directory, found by `diagnose`.
+#[derive(Debug)]
+pub enum Diagnostic {
+    /// The configuration itself is invalid.
+    InvalidConfig(ConfigError),
+    /// The directory does not exist.
+    DirNotFound(PathBuf),
+    /// The directory is outside the allowed roots.
+    DirNotAllowed(PathBuf),
+    /// The directory cannot be read.
+    DirUnreadable(PathBuf, io::Error),
+    /// A series uses counters of a different width than sequential_padding_len, so its files are
+    /// not taken as sequential.
+    PaddingMismatch { base_name: String, found: usize, configured: usize },
+    /// A series has counters over sequential_max_number.
+    CounterOverMax { base_name: String, counter: u64, max: u16 },
+    /// There are no files with the configured extension, but there are files with other ones.
+    OtherExtensions(Vec<String>),
+}
 
 // --------------------------------------------------------------------------------
 // Implementations
 // --------------------------------------------------------------------------------
 
+impl From<bool> for MenuMode {
+    /// Mode of the former is_saving flag.
+    fn from(is_saving: bool) -> Self {
+        if is_saving { MenuMode::Save } else { MenuMode::Load }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// The English message, see Messages::diagnostic to print it in another language.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&EnglishMessages.diagnostic(self))
+    }
+}
+
+impl ConflictAction {
+    /// All the actions of the conflict menu, in menu order.
+    pub const ALL: [ConflictAction; 5] = [
+        ConflictAction::Replace,
+        ConflictAction::MoveExisting,
+        ConflictAction::SequentialNew,
+        ConflictAction::NewName,
+        ConflictAction::Delete,
+    ];
+
+    /// Key to select the action in the interactive menu.
+    pub fn key(&self) -> &'static str {
+        match self {
+            ConflictAction::Replace => "r",
+            ConflictAction::MoveExisting => "m",
+            ConflictAction::SequentialNew => "c",
+            ConflictAction::NewName => "n",
+            ConflictAction::Delete => "d",
+            ConflictAction::Fail => "f",
+        }
+    }
+
+    /// Short description of the action in English, see Messages::conflict_action to get it in
+    /// another language.
+    pub fn description(&self) -> String {
+        EnglishMessages.conflict_action(*self)
+    }
+
+    /// Gets the action selected by its key.
+    pub fn from_key(key: &str) -> Option<ConflictAction> {
+        ConflictAction::ALL.into_iter().find(|action| action.key() == key)
+    }
+
+    /// Checks if the action changes existing files.
+    pub fn modifies_disk(&self) -> bool {
+        !matches!(self, ConflictAction::SequentialNew | ConflictAction::NewName |
+                  ConflictAction::Fail)
+    }
+}
+
 // ----------------------------------------
 // Path handling
 // ----------------------------------------
 
-/// Gets a list of files in the specified path.
-fn get_file_list(path: &Path) -> Result<Vec<String>> {
-    if path.is_dir() {
-        Ok(fs::read_dir(path)?
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.path().is_file())
-            .filter(|entry| entry.path().extension().unwrap_or_default().to_str()
-                .unwrap_or_default() == DEFAULT_MAP_TYPE)
-            .filter_map(|entry| entry.path().file_name()
-                .map(|name| name.to_string_lossy().into_owned()))
-            .collect())
+/// Gets a list of files in the specified path, sorted as configured.
+fn get_file_list(path: &Path, config: &FileManagerConfig) -> Result<Vec<String>> {
+    let entries: FileEntries = get_file_entries_limited(path, usize::MAX, false, config)?;
+    Ok(entries.files.into_iter().map(|(name, _)| name).collect())
+}
+
+/// Gets up to limit files in the specified path with their metadata, sorted as configured.
+/// Every file is sorted before the limit is applied, so that e.g. the newest files are the ones
+/// kept, and the remaining files are only counted.
+///
+/// If include_extensionless is true, files without extension are also collected.
+///
+/// Returns: the files, the number of files that have not been collected, and the names skipped
+///          because they are not valid UTF-8.
+fn get_file_entries_limited(path: &Path, limit: usize, include_extensionless: bool,
+                            config: &FileManagerConfig) -> Result<FileEntries> {
+    if !path.is_dir() {
+        return Ok(FileEntries { files: Vec::new(), omitted: 0, skipped: Vec::new() });
+    }
+    // Metadata is read once per entry, and reused for sorting.
+    let mut files: Vec<(String, Option<fs::Metadata>)> = Vec::new();
+    let mut skipped: Vec<OsString> = Vec::new();
+    for entry in fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| match entry.path().extension() {
+            Some(ext) => ext.to_str() == Some(config.extension.as_str()),
+            None => include_extensionless,
+        })
+    {
+        // Without metadata (e.g. a broken link or a stale network entry), the type of the entry
+        // is taken from the dir listing, and the file is still listed.
+        let metadata: Option<fs::Metadata> = fs::metadata(entry.path()).ok();
+        let is_file: bool = match &metadata {
+            Some(metadata) => metadata.is_file(),
+            None => entry.file_type().is_ok_and(|file_type| !file_type.is_dir()),
+        };
+        if is_file {
+            // Names are handled as strings, a lossy name would not match the file on disk.
+            match entry.file_name().into_string() {
+                Ok(name) => files.push((name, metadata)),
+                Err(name) => skipped.push(name),
+            }
+        }
+    }
+    sort_file_list(&mut files, config.sort_order);
+    let omitted: usize = files.len().saturating_sub(limit);
+    files.truncate(limit);
+    Ok(FileEntries { files, omitted, skipped })
+}
+
+/// Gets the names of the sub directories in the specified path, sorted by name.
+fn get_dir_names(path: &Path) -> Result<Vec<OsString>> {
+    let mut dirs: Vec<OsString> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Gets the names of the sub directories in the specified path, sorted by name.
+///
+/// Returns: the names, and the names skipped because they are not valid UTF-8, for the caller to
+///          report them.
+fn get_dir_list(path: &Path) -> Result<(Vec<String>, Vec<OsString>)> {
+    let mut dirs: Vec<String> = Vec::new();
+    let mut skipped: Vec<OsString> = Vec::new();
+    for name in get_dir_names(path)? {
+        match name.into_string() {
+            Ok(name) => dirs.push(name),
+            Err(name) => skipped.push(name),
+        }
+    }
+    Ok((dirs, skipped))
+}
+
+/// Creates a dir and its missing parents.
+///
+/// Returns: Error::DirCreateFailed with the path if it cannot be created.
+fn create_dir(path: impl AsRef<Path>) -> Result<()> {
+    fs::create_dir_all(path.as_ref()).map_err(|source| Error::DirCreateFailed {
+        path: path.as_ref().to_path_buf(),
+        source,
+    })
+}
+
+/// Gets the parent of a dir, ending with '/'. None at the filesystem root.
+fn get_parent_dir(dir: &Path) -> Option<String> {
+    match dir.components().next_back()? {
+        Component::Normal(_) => {
+            let parent: Cow<str> = dir.parent()?.to_string_lossy();
+            if parent.is_empty() {
+                Some("./".to_string())
+            } else {
+                Some(normalize_dir(&format!("{parent}/")))
+            }
+        },
+        // The parent of "." or ".." can only be reached going up.
+        Component::CurDir | Component::ParentDir =>
+            Some(normalize_dir(&format!("{}/../", dir.to_string_lossy()))),
+        Component::RootDir | Component::Prefix(_) => None,
+    }
+}
+
+/// Sorts the files with their already read metadata.
+/// The name is used as tie breaker, so files without modification time are sorted by name. For
+/// the metadata orders, the files without metadata go last, sorted by name.
+fn sort_file_list(files: &mut [(String, Option<fs::Metadata>)], sort_order: SortOrder) {
+    match sort_order {
+        SortOrder::NameAsc => files.sort_by(|(a, _), (b, _)| a.cmp(b)),
+        SortOrder::NameDesc => files.sort_by(|(a, _), (b, _)| b.cmp(a)),
+        _ => files.sort_by(|(a, a_meta), (b, b_meta)| match (a_meta, b_meta) {
+            (Some(a_meta), Some(b_meta)) =>
+                compare_metadata(a_meta, b_meta, sort_order).then_with(|| a.cmp(b)),
+            (Some(_), None) => cmp::Ordering::Less,
+            (None, Some(_)) => cmp::Ordering::Greater,
+            (None, None) => a.cmp(b),
+        }),
+    }
+}
+
+/// Compares the metadata of two files for a metadata sort order, names are equal otherwise.
+fn compare_metadata(a: &fs::Metadata, b: &fs::Metadata, sort_order: SortOrder) -> cmp::Ordering {
+    match sort_order {
+        SortOrder::NameAsc | SortOrder::NameDesc => cmp::Ordering::Equal,
+        SortOrder::MtimeNewest => b.modified().ok().cmp(&a.modified().ok()),
+        SortOrder::MtimeOldest => a.modified().ok().cmp(&b.modified().ok()),
+        SortOrder::SizeDesc => b.len().cmp(&a.len()),
+    }
+}
+
+/// Gets the list of files to be shown in the menu, up to max_listed files.
+/// Directories without read permission are listed as empty, instead of failing, so that the user
+/// can still navigate out of them.
+/// Files without extension are only listed when loading, if include_extensionless is set, or
+/// always if require_extension is not set.
+/// The parent dir is listed first in the sub dirs, except at the filesystem root.
+/// The recent files are only listed when loading.
+fn get_menu_file_list(path: &Path, mode: MenuMode, config: &FileManagerConfig)
+    -> Result<MenuFileList> {
+    let include_extensionless: bool = (config.include_extensionless && mode == MenuMode::Load) ||
+        !config.require_extension;
+    let (dirs, mut skipped): (Vec<String>, Vec<OsString>) = get_dir_list(path).unwrap_or_default();
+    let sub_dirs: Vec<String> = get_parent_dir(path)
+        .map(|_| "../".to_string())
+        .into_iter()
+        .chain(dirs.into_iter().map(|dir| format!("{dir}/")))
+        .collect();
+    match get_file_entries_limited(path, config.max_listed, include_extensionless, config) {
+        Ok(FileEntries { files, omitted, skipped: skipped_files }) => Ok(MenuFileList {
+            sub_dirs,
+            files: files.iter().map(|(name, _)| name.clone()).collect(),
+            omitted,
+            empty_note: None,
+            filter: SeriesFilter::All,
+            recent: match mode {
+                MenuMode::Load => get_recent_files(&files, config),
+                MenuMode::Save => Vec::new(),
+            },
+            dir: path.to_path_buf(),
+            metadata: files.into_iter()
+                .filter_map(|(name, metadata)| Some((name, metadata?)))
+                .collect(),
+            skipped: {
+                skipped.extend(skipped_files);
+                skipped
+            },
+        }),
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::PermissionDenied =>
+            Ok(MenuFileList {
+                sub_dirs,
+                files: Vec::new(),
+                omitted: 0,
+                empty_note: Some(config.messages.permission_denied_note()),
+                filter: SeriesFilter::All,
+                recent: Vec::new(),
+                dir: path.to_path_buf(),
+                metadata: HashMap::new(),
+                skipped,
+            }),
+        Err(e) => Err(e),
+    }
+}
+
+/// Gets the recent_count most recently modified files with the configured extension, newest
+/// first, from the already collected files and their metadata.
+fn get_recent_files(files: &[(String, Option<fs::Metadata>)], config: &FileManagerConfig)
+    -> Vec<String> {
+    let mut recent: Vec<(SystemTime, &String)> = files
+        .iter()
+        .filter(|(name, _)| Path::new(name).extension()
+            .is_some_and(|ext| ext == config.extension.as_str()))
+        .filter_map(|(name, metadata)| Some((metadata.as_ref()?.modified().ok()?, name)))
+        .collect();
+    recent.sort_by(|a, b| b.cmp(a));
+    recent.into_iter().take(config.recent_count).map(|(_, name)| name.clone()).collect()
+}
+
+/// Gets the configuration of a dir, with the overrides of its DIR_CONFIG_FILE if dir_overrides is
+/// set. An invalid config file is ignored with a warning, so the menu keeps the given
+/// configuration.
+fn get_dir_config<'a>(dir: &str, config: &'a FileManagerConfig) -> Cow<'a, FileManagerConfig> {
+    if !config.dir_overrides {
+        return Cow::Borrowed(config);
+    }
+    match config.with_dir_overrides(Path::new(dir)) {
+        Ok(Some(dir_config)) => Cow::Owned(dir_config),
+        Ok(None) => Cow::Borrowed(config),
+        Err(e) => {
+            println!("{}", config.messages.error(&e));
+            let file: PathBuf = Path::new(dir).join(DIR_CONFIG_FILE);
+            println!("{}", config.messages.dir_config_ignored(&file.to_string_lossy()));
+            Cow::Borrowed(config)
+        },
+    }
+}
+
+/// Gets the numbered entries of the menu listing: the default dirs, the sub dirs of the current
+/// dir, the files and the recent files.
+fn get_listing_entries(paths: &[String], current_dir: &str, listing: &MenuFileList)
+    -> Vec<ListingEntry> {
+    let sub_dirs = listing.sub_dirs.iter().filter_map(|dir| match dir.as_str() {
+        "../" => get_parent_dir(Path::new(current_dir)),
+        _ => Some(Path::new(current_dir).join(dir).to_string_lossy().into_owned()),
+    });
+    paths.iter()
+        .cloned()
+        .chain(sub_dirs)
+        .map(ListingEntry::Dir)
+        .chain(listing.files.iter().chain(&listing.recent).cloned().map(ListingEntry::File))
+        .collect()
+}
+
+/// Keeps only the listed files that pass the series filter.
+fn filter_series(listing: &mut MenuFileList, filter: SeriesFilter, config: &FileManagerConfig) {
+    if filter == SeriesFilter::All {
+        return;
+    }
+    let has_files: bool = !listing.files.is_empty();
+    if filter == SeriesFilter::Latest {
+        listing.files = get_latest_per_series(&listing.files, config);
     } else {
-        Ok(Vec::new())
+        let keep_series: bool = filter == SeriesFilter::Series;
+        listing.files.retain(|file| (is_sequential_name(file, config) != *file) == keep_series);
+    }
+    if has_files && listing.files.is_empty() {
+        listing.empty_note = Some(config.messages.filtered_note());
     }
+    listing.filter = filter;
 }
 
 /// Prints the list of options in a generic way.
 /// The options are numbered and placed in multiple columns so that the user can easily select the
 /// desired option.
-/// List of notes:
-///     1. counter width should match the number of numbers of MAX_SEQUENTIAL_FILE_NUMBER.
-///     2. option string width should the maximum allowed size defined by MAX_FILE_NAME_CHARS.
-fn print_option_list(opts: &[String], empty_note: &str, start: usize) {
+/// The counter (right aligned) and option (left aligned) widths come from the configuration, by
+/// default they match the digits of the max sequential number and MAX_FILE_NAME_CHARS.
+fn print_option_list(out: &mut dyn io::Write, opts: &[String], empty_note: &str, start: usize,
+                     config: &FileManagerConfig) -> io::Result<()> {
     if opts.is_empty() {
-        println!("    {}", empty_note);
+        writeln!(out, "    {}", empty_note)?;
     }
     for (cnt, opt) in opts.iter().enumerate() {
         let abs_cnt = cnt + start;
-        if cnt % PRINT_COLUMNS == 0 {
-            print!("    ");
+        if cnt % config.print_columns == 0 {
+            write!(out, "    ")?;
         }
-        print!("{: >3}: {: <30}", abs_cnt, opt); // Notes 1, 2
-        if (cnt + 1) % PRINT_COLUMNS == 0 {
-            println!();
+        write!(out, "{: >number_width$}: {: <name_width$}", abs_cnt, opt,
+               number_width = config.number_width, name_width = config.name_width)?;
+        if (cnt + 1) % config.print_columns == 0 {
+            writeln!(out)?;
         }
     }
-    if opts.len() % PRINT_COLUMNS != 0 {
-        println!();
+    if !opts.len().is_multiple_of(config.print_columns) {
+        writeln!(out)?;
     }
+    Ok(())
 }
 
 /// List files in the selected directory.
-fn print_dir_files(files: &[String], start: usize) {
-    print_option_list(files, "(Empty directory)", start);
+/// When loading, the names are shown without the name template. The extension is hidden if
+/// show_extensions is not set.
+fn print_dir_files(out: &mut dyn io::Write, listing: &MenuFileList, start: usize, mode: MenuMode,
+                   config: &FileManagerConfig) -> io::Result<()> {
+    let empty_note: &str = listing.empty_note.as_deref().unwrap_or(&config.empty_files_note);
+    print_option_list(out, &get_listed_names(&listing.files, mode, config), empty_note, start,
+                      config)?;
+    if listing.omitted > 0 {
+        writeln!(out, "{}", config.messages.omitted_files(listing.omitted))?;
+    }
+    Ok(())
+}
+
+/// Gets the names of the listed files as they are printed.
+fn get_listed_names(files: &[String], mode: MenuMode, config: &FileManagerConfig)
+    -> Vec<String> {
+    files
+        .iter()
+        .map(|file| {
+            let file: String = match mode {
+                MenuMode::Save => file.clone(),
+                MenuMode::Load => strip_name_template(file, config).unwrap_or_else(|| file.clone()),
+            };
+            if config.show_extensions {
+                return file;
+            }
+            // Other extensions (or none) are kept, so that the files can be told apart.
+            let stem: Option<&str> = file
+                .strip_suffix(config.extension.as_str())
+                .and_then(|stem| stem.strip_suffix('.'));
+            stem.map_or_else(|| file.clone(), str::to_string)
+        })
+        .collect()
 }
 
 /// Prints the default paths.
 /// List of notes:
 ///     1. Counter width should match the number of numbers of MAX_SEQUENTIAL_FILE_NUMBER.
 ///     2. File name string width should the maximum allowed size defined by MAX_PATH_NAME_CHARS.
-fn print_paths(paths: &[String], start: usize) {
-    print_option_list(paths, "(No directories)", start);
+fn print_paths(out: &mut dyn io::Write, paths: &[String], start: usize, config: &FileManagerConfig)
+    -> io::Result<()> {
+    print_option_list(out, paths, &config.empty_dirs_note, start, config)
 }
 
 // ----------------------------------------
-// Sequential naming
+// Trash
 // ----------------------------------------
 
-/// Gets the sequential name of the file from its base name and the current count.
-/// Note that the base_name will already have the trailing "_", so there is no need to add it.
-fn get_sequential_name_from_count(base_name: &str, cnt: u16) -> String {
-    format!("{}{:0>3}.{}", base_name, cnt, DEFAULT_MAP_TYPE)
+/// Moves a file into the trash directory instead of removing it.
+/// If the name is already taken in the trash, the file is stored with a counter suffix so that
+/// previously trashed files are never overwritten (see get_trash_name).
+///
+/// Returns: the path of the file inside the trash, Error::InvalidSequentialName if every counter
+///          up to sequential_max_number is taken.
+fn move_to_trash(full_path: &Path, file_name: &str, config: &FileManagerConfig) -> Result<PathBuf> {
+    create_dir(&config.trash_dir)?;
+    // A name that already looks suffixed gets a suffix of its own, so it is restored as it was.
+    let first: u16 = if get_trash_original_name(file_name, config).is_some() { 1 } else { 0 };
+    for cnt in first..=config.sequential_max_number {
+        let trash_path: PathBuf = config.trash_dir.join(get_trash_name(file_name, cnt, config));
+        match move_file_no_clobber(full_path, &trash_path) {
+            Ok(()) => return Ok(trash_path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {},
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(Error::InvalidSequentialName(config.sequential_max_number))
 }
 
-/// Searches the files to get the next sequential name.
-/// if next is true the next unused name is returned; otherwise the last used name.
-fn get_sequential_name(current_path:&str, base_name:&str, next:bool) -> Result<String> {
-    let mut cnt_max: u16 = 0;
-    let mut found: bool = false;
-    let path_name = Path::new(&current_path);
-    let file_list: Vec<String> = get_file_list(path_name)?;
-    for name in file_list
-        .iter()
-        .filter(|entry|
-            &entry[0..entry.len()-(DEFAULT_MAP_TYPE.len() + 1 + SEQUENTIAL_FILE_PADDING_LEN)] ==
-            base_name)
-    {
-        let cnt = name
-            .split(SEQUENTIAL_NAMING_CHAR).last().unwrap_or_default()
-            .split('.').next().unwrap_or_default();
-        if let Ok(cnt) = cnt.parse::<u16>() {
-            found = true;
-            if cnt > cnt_max {
-                cnt_max = cnt;
-            }
-        }
+/// Gets the name of a file in the trash: its own name for cnt 0, or its name with a sequential
+/// counter before its own extension, e.g. "forest~002.meta" for "forest.meta". Files without
+/// extension get the counter at the end.
+fn get_trash_name(file_name: &str, cnt: u16, config: &FileManagerConfig) -> String {
+    if cnt == 0 {
+        return file_name.to_string();
     }
-    if next && found {
-        cnt_max += 1;
+    let path: &Path = Path::new(file_name);
+    let ext: &str = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    let stem: &str = file_name.strip_suffix(ext).unwrap_or(file_name).trim_end_matches('.');
+    let trash_config: FileManagerConfig = FileManagerConfig {
+        sequential_mode: SequentialMode::Counter,
+        sequential_padding: true,
+        extension: ext.to_string(),
+        ..config.clone()
+    };
+    let name: String = get_sequential_name_from_count(&format!("{stem}{TRASH_SUFFIX_CHAR}"), cnt,
+                                                      &trash_config);
+    match name.strip_suffix('.') {
+        Some(name) => name.to_string(), // No extension.
+        None => name,
     }
+}
 
-    if cnt_max <= SEQUENTIAL_FILE_MAX_NUMBER {
-        Ok(get_sequential_name_from_count(base_name, cnt_max))
-    } else {
-        Err(Error::InvalidSequentialName)
+/// Gets the original name of a file stored in the trash with a counter suffix, e.g. "forest.meta"
+/// for "forest~002.meta".
+///
+/// Returns: None if the name has no counter suffix, so it is its own original name.
+fn get_trash_original_name(trash_name: &str, config: &FileManagerConfig) -> Option<String> {
+    let ext: &str = Path::new(trash_name).extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    let stem: &str = trash_name.strip_suffix(ext).unwrap_or(trash_name).trim_end_matches('.');
+    let (original, cnt): (&str, &str) = stem.rsplit_once(TRASH_SUFFIX_CHAR)?;
+    if original.is_empty() || cnt.len() != config.sequential_padding_len ||
+        !cnt.chars().all(|c| c.is_ascii_digit()) {
+        return None;
     }
+    Some(if ext.is_empty() { original.to_string() } else { format!("{original}.{ext}") })
 }
 
-/// If the name is sequential, return basename only.
-/// Sequential names end in <base_name>_XXX.<extension>.
-fn is_sequential_name(file_name: String) -> String {
-    let (base_name, _) = file_name.split_once('.').unwrap_or_default();
-    if let Some(cnt) = base_name.split('_').last() {
-        if cnt.len() == SEQUENTIAL_FILE_PADDING_LEN && cnt.parse::<u16>().is_ok() {
-            let last_index: usize = file_name.len() - (DEFAULT_MAP_TYPE.len() + 1 + SEQUENTIAL_FILE_PADDING_LEN);
-            return file_name[0..(last_index)].to_string();
-        }
+/// Moves a file without replacing the destination, failing with io::ErrorKind::AlreadyExists if
+/// it exists. The file is hard linked and then removed, so that the check and the move cannot be
+/// split by another process. Where it cannot be linked (e.g. on another file system), the
+/// destination is created with create_new and the content copied.
+fn move_file_no_clobber(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::hard_link(from, to) {
+        Ok(()) => fs::remove_file(from),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(e),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(e),
+        Err(_) => {
+            let mut source: fs::File = fs::File::open(from)?;
+            let mut dest: fs::File = fs::OpenOptions::new().write(true).create_new(true).open(to)?;
+            io::copy(&mut source, &mut dest)?;
+            dest.set_permissions(source.metadata()?.permissions())?;
+            fs::remove_file(from)
+        },
+    }
+}
+
+/// Gets the names of the existing sidecar files of a file, e.g. "forest_007.meta" for
+/// "forest_007.map", with their extension.
+fn get_sidecars(dir: &Path, file_name: &str, config: &FileManagerConfig) -> Vec<(String, String)> {
+    config.sidecar_extensions
+        .iter()
+        .filter_map(|ext| {
+            let name: String = sidecar_path(Path::new(file_name), ext).to_str()?.to_string();
+            dir.join(&name).is_file().then(|| (name, ext.clone()))
+        })
+        .collect()
+}
+
+/// Moves a file and its sidecar files to the trash.
+fn move_to_trash_with_sidecars(dir: &Path, file_name: &str, config: &FileManagerConfig)
+    -> Result<PathBuf> {
+    for (sidecar, _) in get_sidecars(dir, file_name, config) {
+        move_to_trash(&dir.join(&sidecar), &sidecar, config)?;
     }
-    file_name
+    move_to_trash(&dir.join(file_name), file_name, config)
 }
 
 // ----------------------------------------
 // Paths
 // ----------------------------------------
 
+/// Resolves a relative path against the configured root, absolute paths are kept as they are.
+/// With the default (empty) root, paths stay relative to the execution path.
+fn resolve_from_root(path: &str, config: &FileManagerConfig) -> String {
+    config.root.join(path).to_string_lossy().into_owned()
+}
+
 /// Initializes the default path list and the current path.
 ///
-/// paths input list of default paths, if None the default path is used.
-/// The current path is the first path on the list.
-fn init_default_paths(paths: Option<Vec<String>>) -> (String, Vec<String>) {
-    let paths: Vec<String> = match paths {
-        Some(path_vec) => {
-            if path_vec.is_empty() {
-                Vec::from([DEFAULT_DIRECTORY.to_string()])
-            } else {
-                path_vec
-            }
-        },
-        None => Vec::from([DEFAULT_DIRECTORY.to_string()]),
+/// paths input list of default paths, if None (or no path is valid) the default path is used.
+/// The current path is the first path on the list. Relative paths are resolved against the root.
+/// All the paths end with exactly one '/', however they were written by the caller.
+fn init_default_paths(paths: Option<Vec<String>>, config: &FileManagerConfig)
+    -> (String, Vec<String>) {
+    // Empty entries are ignored, they would resolve to the execution (or root) dir.
+    let paths: Vec<String> = paths
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|path| !path.trim().is_empty())
+        .collect();
+    let paths: Vec<String> = if paths.is_empty() {
+        Vec::from([DEFAULT_DIRECTORY.to_string()])
+    } else {
+        paths
     };
+    // Exactly one trailing separator, so that names can be appended to any of them.
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|path| format!("{}/", resolve_from_root(path, config).trim_end_matches('/')))
+        .collect();
     let default: String = paths[0].clone();
     (default, paths)
 }
 
-// ----------------------------------------
-// Menus
-// ----------------------------------------
+/// Creates the default dirs that do not exist yet.
+fn ensure_default_paths(paths: &[String], config: &FileManagerConfig) -> Result<()> {
+    for path in paths.iter().filter(|path| !check_dir_exists(path)) {
+        println!("{}", config.messages.creating_default_dir(path));
+        create_dir(path)?;
+    }
+    Ok(())
+}
+
+/// Gets the session dir of auto_session_dir of a dir, if this process has created it and it
+/// still exists.
+fn get_session_dir(parent_dir: &str) -> Option<String> {
+    let session_dirs: MutexGuard<Vec<(String, String)>> =
+        SESSION_DIRS.lock().unwrap_or_else(PoisonError::into_inner);
+    session_dirs
+        .iter()
+        .find(|(dir, session_dir)| dir == parent_dir && check_dir_exists(session_dir))
+        .map(|(_, session_dir)| session_dir.clone())
+}
+
+/// Checks if a dir is a session dir of auto_session_dir created by this process.
+fn is_session_dir(dir: &str) -> bool {
+    let session_dirs: MutexGuard<Vec<(String, String)>> =
+        SESSION_DIRS.lock().unwrap_or_else(PoisonError::into_inner);
+    session_dirs.iter().any(|(_, session_dir)| session_dir == dir)
+}
 
-fn print_menu_options(current_dir: &str, paths: &[String], files: &[String]) {
-    println!("Input the name of the file to be saved:");
-    println!(" - Input a number to preselect a directory or a file.");
-    println!(
-        " - Input a name ending with / to specify a new absolute or relative (from the execution path) path."
-    );
-    println!(" - Press CTRL+C to restart the input.");
-    println!(" - Press CTRL+D to exit (may need to press CTRL+C first).");
-    println!(" - A name ending in _ (e.g. test_), will be transformed into a sequential name.");
-    println!("----\nDefault directories (relative):");
-    print_paths(paths, 0);
-    println!("----\nCurrent dir: {}", current_dir);
-    println!("----\nFiles:");
-    print_dir_files(files, paths.len());
-}
-
-fn check_file_name_len(name: &str) -> Result<()> {
-    if name.len() > MAX_FILE_NAME_CHARS {
-        Err(Error::InvalidNameTooLong)
+/// Gets the path of a new session dir of a dir, which is only created by create_session_dir.
+fn get_new_session_dir(parent_dir: &str, config: &FileManagerConfig) -> String {
+    format!("{}{}{}/", parent_dir, SESSION_DIR_PREFIX, current_timestamp(config))
+}
+
+/// Creates the session dir of a dir, which the later saves into the dir go to.
+fn create_session_dir(parent_dir: &str, session_dir: &str, config: &FileManagerConfig)
+    -> Result<()> {
+    println!("{}", config.messages.creating_session_dir(session_dir));
+    create_dir(session_dir)?;
+    let mut session_dirs: MutexGuard<Vec<(String, String)>> =
+        SESSION_DIRS.lock().unwrap_or_else(PoisonError::into_inner);
+    session_dirs.retain(|(dir, _)| dir != parent_dir);
+    session_dirs.push((parent_dir.to_string(), session_dir.to_string()));
+    Ok(())
+}
+
+/// Checks if the saves of a menu go to the session dir of auto_session_dir.
+fn is_session_save(mode: MenuMode, config: &FileManagerConfig) -> bool {
+    config.auto_session_dir && !config.read_only && mode == MenuMode::Save
+}
+
+/// Reads the last used directory from the state file.
+/// Returns None if there is no stored directory or if it no longer exists.
+fn load_last_dir(config: &FileManagerConfig) -> Option<String> {
+    let state: String = fs::read_to_string(&config.state_file).ok()?;
+    let dir: &str = state.trim_end_matches('\n');
+    if !dir.is_empty() && check_dir_exists(dir) {
+        Some(dir.to_string())
     } else {
-        Ok(())
+        None
     }
 }
 
-/// Checks if file exists
-fn check_file_exists(path: &str, file_name: String, is_saving:bool) -> Result<String> {
-    let full_path: PathBuf = Path::new(path).join(&file_name);
-    if full_path.is_file() && is_saving {
-        println!("FILE MNG :: file {} already exits while saving.", full_path.to_string_lossy());
-        println!("Input:");
-        println!("  'r' to replace existing file.");
-        println!("  'm' to turn existing file into sequential naming.");
-        println!("  'c' to turn new file into sequential naming.");
-        println!("  'n' to select a new name.");
-        println!("  'd' to delete the specified file.");
-
-        // run editor:
-        let mut rl = rustyline::DefaultEditor::new()?;
-        for c in ["r", "m", "c", "n", "d"] {
-            rl.add_history_entry(c)?;
-        }
-        loop {
-            match rl.readline("> ") {
-                Ok(line) => match line.trim() {
-                    "r" => { // Replace
-                        println!("Replacing {}...", path.display());
-                        fs::remove_file(full_path)?;
-                        return Ok(file_name);
-                    },
-                    "m" => { // Move old file.
-                        let (base_name, _) = file_name.split_once('.')
-                            .ok_or(Error::UnknownFileType)?;
-                        let base_name = format!("{}{}", base_name, '_');
-                        let new_name: String = get_sequential_name(path, &base_name, true)?;
-                        println!("Renaming {} to {}{}",
-                                full_path.display(), path.display(), new_name.display());
-                        fs::rename(full_path, format!("{}{}", path, new_name))?;
-                        return Ok(file_name);
-                    },
-                    "c" => { // rename new file.
-                        let (base_name, _) = file_name.split_once('.')
-                            .ok_or(Error::UnknownFileType)?;
-                        let base_name = format!("{}{}", base_name, '_');
-                        let new_name: String = get_sequential_name(path, &base_name, true)?;
-                        return Ok(new_name);
-                    },
-                    "n" => {
-                        return Err(Error::NeedNewName);
-                    },
-                    "d" => {
-                        fs::remove_file(&full_path)?;
-                        println!("File {} has been deleted.", full_path.display());
-                        return Err(Error::FileDeletion);
-                    },
-                    _ => println!("Invalid input, try again."),
-                },
-                Err(ReadlineError::Interrupted) => { // CTRL+C
-                    return Err(Error::NeedNewName);
-                },
-                Err(ReadlineError::Eof) => { // CTRL+D
-                    return Err(Error::ManuallyTerminated);
-                },
-                Err(err) => {
-                    println!("FILE MNG :: ERROR :: failed due to {err}");
-                }
-            }
-        }
-    } else if !full_path.is_file() && !is_saving {
-        println!("FILE MNG :: file {} does not exists while loading.", full_path.to_string_lossy());
-        return Err(Error::NeedNewName);
+/// Stores the last used directory in the state file.
+/// The file is not written again when it already holds the same directory.
+#[cfg(feature = "interactive")]
+fn save_last_dir(current_path: &str, config: &FileManagerConfig) -> Result<()> {
+    if load_last_dir(config).as_deref() == Some(current_path) {
+        return Ok(());
     }
-    Ok(file_name)
+    fs::write(&config.state_file, current_path)?;
+    Ok(())
 }
 
-/// Checks if the specified directory exists or not.
-fn check_dir_exists(path: &str) -> bool {
-    Path::new(path).is_dir()
-}
+// ----------------------------------------
+// Menu commands
+// ----------------------------------------
 
+/// Commands that can be input in the file name menu instead of a name.
+enum MenuCommand {
+    /// Prints the beginning of a file, load mode only.
+    Peek(String),
+    /// Deletes (moves to the trash) a list of files, e.g. "3,5,7-9", load mode only.
+    Delete(String),
+    /// Prints the help banner.
+    Help,
+    /// Reads the current dir again.
+    Refresh,
+    /// Filters the listed files by sequential series.
+    Filter(SeriesFilter),
+    /// Renames all the files of a series to a new base name, keeping their suffixes.
+    RenameSeries(String, String),
+    /// Renames the files of a series to contiguous counters from 1, keeping their order.
+    Renumber(String),
+    /// Searches the files whose name contains the text in all the default dirs.
+    Find(String),
+    /// Prints the counters missing in a series, load mode only.
+    Audit(String),
+    /// Compares the size and modification time of two files.
+    Diff(String, String),
+    /// Narrows the listing to the entries matching a fuzzy pattern, e.g. "/frst".
+    Fuzzy(String),
+    /// Opens the current dir in the file explorer of the OS.
+    #[cfg(feature = "reveal")]
+    Reveal,
+}
 
-/// Launches a menu to ask if yes or no.
-///
-/// Returns: Ok if the user inputs yes, Error::NeedNewName if the user inputs no, or error code
-///          if an error has taken place.
-fn ask_yes_no() -> Result<()>{
-    let mut rl = rustyline::DefaultEditor::new()?;
-    for c in ["y", "yes", "n", "no"] {
-        rl.add_history_entry(c)?;
+/// Parses a menu command: the first word is the command and the rest its argument.
+/// Returns None if the line is not a command available in the current mode.
+fn parse_menu_command(line: &str, mode: MenuMode) -> Option<MenuCommand> {
+    let line: &str = line.trim();
+    let (command, arg): (&str, &str) = match line.split_once(' ') {
+        Some((command, arg)) => (command, arg.trim()),
+        None => (line, ""),
+    };
+    if let Some(pattern) = get_fuzzy_pattern(line) {
+        return Some(MenuCommand::Fuzzy(pattern.to_string()));
     }
-    println!("Input <y>/<yes> or <n>/<no>:");
-    loop {
-        match rl.readline("> ") {
-            Ok(line) => match line.trim() {
-                "y" | "yes" => {
-                    return Ok(());
-                },
-               "n" | "no" => {
-                    return Err(Error::NeedNewName);
-               },
-                _ => println!("Invalid input, try again."),
-            },
-            Err(ReadlineError::Interrupted) => { // CTRL+C
-                return Err(Error::NeedNewName);
-            },
-            Err(ReadlineError::Eof) => { // CTRL+D
-                return Err(Error::ManuallyTerminated);
-            },
-            Err(err) => {
-                println!("FILE MNG :: ERROR :: failed due to {err}");
-                return Err(Error::NeedNewName);
-            }
-        }
+    match command {
+        "peek" | "view" if mode == MenuMode::Load && !arg.is_empty() => Some(MenuCommand::Peek(arg.to_string())),
+        "del" if mode == MenuMode::Load && !arg.is_empty() => Some(MenuCommand::Delete(arg.to_string())),
+        "audit" if mode == MenuMode::Load && !arg.is_empty() => Some(MenuCommand::Audit(arg.to_string())),
+        "help" if arg.is_empty() => Some(MenuCommand::Help),
+        "refresh" | "r!" if arg.is_empty() => Some(MenuCommand::Refresh),
+        "find" if !arg.is_empty() => Some(MenuCommand::Find(arg.to_string())),
+        "diff" => arg.split_once(' ').map(|(first, second)| {
+            MenuCommand::Diff(first.to_string(), second.trim().to_string())
+        }),
+        "renameseries" => arg.split_once(' ').map(|(old_base, new_base)| {
+            MenuCommand::RenameSeries(old_base.to_string(), new_base.trim().to_string())
+        }),
+        "renumber" if !arg.is_empty() => Some(MenuCommand::Renumber(arg.to_string())),
+        "filter" => match arg {
+            "all" => Some(MenuCommand::Filter(SeriesFilter::All)),
+            "series" => Some(MenuCommand::Filter(SeriesFilter::Series)),
+            "single" => Some(MenuCommand::Filter(SeriesFilter::Single)),
+            "latest" => Some(MenuCommand::Filter(SeriesFilter::Latest)),
+            _ => None,
+        },
+        #[cfg(feature = "reveal")]
+        "reveal" if arg.is_empty() => Some(MenuCommand::Reveal),
+        _ => None,
     }
 }
 
-/// Checks if an input is a path, a filename or both.
-///
-/// param line: User input.
+/// Gets the pattern of a fuzzy filter input: a single '/' followed by the pattern, so that absolute
+/// paths (e.g. "/maps/") are not taken.
+pub(crate) fn get_fuzzy_pattern(line: &str) -> Option<&str> {
+    line.strip_prefix('/').filter(|pattern| !pattern.is_empty() && !pattern.contains('/'))
+}
+
+/// Resolves a command argument into a file of the current dir: either the number of a listed
+/// file, or a file name (the extension is added if missing).
+fn resolve_file_arg(arg: &str, current_path: &str, entries: &[ListingEntry],
+                    config: &FileManagerConfig) -> Option<PathBuf> {
+    let name: String = match arg.parse::<usize>() {
+        Ok(num) => match entries.get(num)? {
+            ListingEntry::File(name) => name.clone(),
+            ListingEntry::Dir(_) => return None,
+        },
+        Err(_) if arg.contains('.') => arg.to_string(),
+        Err(_) => format!("{}.{}", arg, config.extension),
+    };
+    Some(Path::new(current_path).join(name))
+}
+
+/// Parses a list of listing numbers and ranges, e.g. "3,5,7-9", that must be in [start, end).
 ///
-/// Return tuple:
-///     - Path option: if none the dir has not been changed.
-///     - Path option: if none there is no valid file name.
-fn check_if_path_or_file(line: &str) -> (Option<String>, Option<String>) {
-    if line.is_empty() {
-        (None, None)
-    } else if line.chars().last().unwrap_or_default() == '/' { // Only directory.
-        (Some(line.to_string()), None)
-    } else {
-        match line.rsplit_once('/') {
-            Some((d, f)) => (Some(format!("{d}/")), Some(f.to_string())), // Is path with directory.
-            None => (None, Some(line.to_string())), // Only file.
+/// Returns: the sorted numbers without duplicates, or a message for the first invalid entry.
+fn parse_number_list(arg: &str, start: usize, end: usize, messages: &dyn Messages)
+    -> result::Result<Vec<usize>, String> {
+    let mut numbers: Vec<usize> = Vec::new();
+    for entry in arg.split(',').map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+        let (first, last): (&str, &str) = entry.split_once('-').unwrap_or((entry, entry));
+        let (first, last): (usize, usize) = match (first.trim().parse(), last.trim().parse()) {
+            (Ok(first), Ok(last)) if first <= last => (first, last),
+            _ => return Err(messages.invalid_number_entry(entry)),
+        };
+        if first < start || last >= end {
+            return Err(messages.number_entry_out_of_range(entry, start, end.saturating_sub(1)));
         }
+        numbers.extend(first..=last);
     }
+    numbers.sort_unstable();
+    numbers.dedup();
+    Ok(numbers)
 }
 
-/// Parses the menu inputs.
-/// Outputs:
-///     - Path option: if none the dir has not been changed.
-///     - Path option: if none there is no valid file name.
-fn parse_menu_file(line: &str,  dirs: &[String], files: &[String]) -> Result<(Option<String>, Option<String>)> {
-    let path: Option<String>;
-    let file_name: Option<String>;
-
-    if line.is_empty() {
-        // Empty input -> return
-        println!("Empty input, try again.");
-        file_name = None;
-        path = None;
-    } else if let Ok(num) = line.parse::<usize>() {
-        // Number input --> load existing name.
-        if num < dirs.len() {
-            if dirs[num].chars().last().unwrap_or_default() == '/' {
-                path = Some(dirs[num].to_string());
-            } else {
-                path = Some(format!("{}{}", dirs[num], "/"));
-            }
-            file_name = None;
-        } else if num - dirs.len() < files.len() {
-            let n = num - dirs.len();
-            path = None;
-            file_name = Some(is_sequential_name(files[n].to_string()));
-        } else {
-            path = None;
-            file_name = None;
-            println!("{} is out of range, try again.", num);
+/// Prints the first peek_bytes of a file.
+/// Binary data is printed as a hex dump, so that the terminal is not garbled.
+fn peek_file(path: &Path, config: &FileManagerConfig) -> Result<()> {
+    let mut bytes: Vec<u8> = Vec::new();
+    fs::File::open(path)?.take(config.peek_bytes as u64).read_to_end(&mut bytes)?;
+    println!("{}", config.messages.peek_header(path, bytes.len()));
+    let is_binary: bool = bytes
+        .iter()
+        .any(|&b| b == 0x7f || (b < 0x20 && !matches!(b, b'\n' | b'\r' | b'\t')));
+    if is_binary {
+        for (cnt, chunk) in bytes.chunks(16).enumerate() {
+            let hex: String = chunk.iter().map(|b| format!("{:02x} ", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            println!("    {:08x}  {: <48} |{}|", cnt * 16, hex, ascii);
         }
     } else {
-        // Path and/or file name.
-        (path, file_name) = check_if_path_or_file(line);
+        println!("{}", String::from_utf8_lossy(&bytes));
     }
-    Ok((path, file_name))
+    println!("----");
+    Ok(())
 }
 
-/// Launches the file name selection menu.
-/// If is saving is:
-///     True:
-///         - Sequential naming will yield the next unused name.
-///         - It will fail if the selected name does not exist.
-///     False: thus, is loading a file.
-///         - Sequential naming will yield the last used name.
-///         - If the selected name already exists it will run the rename menu.
-fn file_name_menu(current_path: String, paths: &[String], is_saving:bool) -> Result<String> {
-    let mut rl = rustyline::DefaultEditor::new()?;
-    let mut init_s: String = String::from("");
-    //let mut running: bool = true;
-
-    let mut current_path: String = current_path;
-
-    'dir_loop: loop {
-        let path_name = Path::new(&current_path);
-        let file_list: Vec<String> = get_file_list(path_name)?;
-        print_menu_options(&current_path, paths, &file_list);
-
-        rl.clear_history()?;
-        for f in file_list.iter().rev() {
-            rl.add_history_entry(f)?;
-        }
-        for p in paths.iter().rev() {
-            rl.add_history_entry(p)?;
-        }
-
-        'file_loop: loop {
-            let readline =
-                rl.readline_with_initial("> ", (&init_s, ""));
-
-            match readline {
-                Ok(line) => {
-                    let line: String = line.split(' ')
-                        .filter(|s| !s.is_empty())
-                        .collect::<Vec<_>>()
-                        .join("_");
-                    let l: &str = &line;
-                    rl.add_history_entry(&line)?;
-
-                    let (path, file): (Option<String>, Option<String>) =
-                        parse_menu_file(l, paths, &file_list)?;
-
-                    let path_updated: bool;
-                    let path = match path {
-                        Some(p) => p,
-                        None => current_path.clone(),
-                    };
-                    if !check_dir_exists(&path) {
-                        // Selected path does not exist.
-                        println!("Selected path does not exists: {}", &path);
-                        if is_saving {
-                            // ask if the new dir needs to be created or not.
-                            println!("Create new dir?");
-                            match ask_yes_no() {
-                                Ok(()) => {
-                                    current_path = path;
-                                    let new_dir_path = Path::new(&current_path);
-                                    fs::create_dir_all(new_dir_path)?;
-                                    path_updated = true;
-                                },
-                                Err(Error::NeedNewName) => {
-                                    println!("New directory not created, input a new one.");
-                                    continue 'file_loop;
-                                }
-                                Err(e) => return Err(e),
-                            };
-                        } else {
-                            continue 'file_loop;
-                        }
-                    } else {
-                        current_path = path;
-                        path_updated = true;
-                    }
+/// Prints the size and modification time of two files, and which one is newer and larger.
+fn diff_files(first: &Path, second: &Path, messages: &dyn Messages) -> Result<()> {
+    let first_meta: fs::Metadata = fs::metadata(first)?;
+    let second_meta: fs::Metadata = fs::metadata(second)?;
+    let mtime = |meta: &fs::Metadata| meta.modified().ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_secs());
+    println!("{}", messages.diff_header());
+    for (path, meta) in [(first, &first_meta), (second, &second_meta)] {
+        let modified: Option<String> = mtime(meta).map(format_timestamp);
+        println!("{}", messages.diff_file(path, meta.len(), modified.as_deref()));
+    }
+    let name = |path: &Path| path.file_name().map_or_else(String::new,
+                                                          |name| name.to_string_lossy().into_owned());
+    match mtime(&first_meta).cmp(&mtime(&second_meta)) {
+        cmp::Ordering::Greater => println!("{}", messages.diff_newer(&name(first))),
+        cmp::Ordering::Less => println!("{}", messages.diff_newer(&name(second))),
+        cmp::Ordering::Equal => println!("{}", messages.diff_same_time()),
+    }
+    match first_meta.len().cmp(&second_meta.len()) {
+        cmp::Ordering::Greater => println!("{}", messages.diff_larger(&name(first))),
+        cmp::Ordering::Less => println!("{}", messages.diff_larger(&name(second))),
+        cmp::Ordering::Equal => println!("{}", messages.diff_same_size()),
+    }
+    println!("----");
+    Ok(())
+}
 
-                    if let Some(mut file) = file {
-                        // check sequential naming
-                        if file.chars().last().unwrap_or_default() == SEQUENTIAL_NAMING_CHAR {
-                            println!("Getting sequential name...");
-                            file = get_sequential_name(&current_path, &file, is_saving)?;
-                        }
-                        // Check extension
-                        let file: String = match file.split_once('.') {
-                            Some((s, ext)) => {
-                                if ext == DEFAULT_MAP_TYPE {
-                                    file
-                                } else {
-                                    println!("{}", Error::UnknownFileType);
-                                    init_s = format!("{s}.{DEFAULT_MAP_TYPE}");
-                                    if path_updated {
-                                        continue 'dir_loop;
-                                    } else {
-                                        continue 'file_loop;
-                                    }
-                                }
-                            },
-                            None => {
-                                format!("{}.{}", file, DEFAULT_MAP_TYPE)
-                            }
-                        };
-                        // Name length
-                        check_file_name_len(&file)?;
-                        // Check if file exists
-                        let file = match check_file_exists(&current_path, file, is_saving) {
-                            Ok(s) => s,
-                            Err(Error::NeedNewName) => {
-                                init_s.clear();
-                                println!("Please input a new name.");
-                                if path_updated {
-                                    continue 'dir_loop;
-                                } else {
-                                    continue 'file_loop;
-                                }
-                            },
-                            Err(e) => return Err(e),
-                        };
-                        return Ok(format!("{}{}", current_path, file));
-                    }
-                    init_s.clear();
-                    if path_updated {
-                        continue 'dir_loop;
-                    }
-                },
-                Err(ReadlineError::Interrupted) => { // CTRL+C
-                    init_s.clear();
-                },
-                Err(ReadlineError::Eof) => { // CTRL+D
-                    return Err(Error::ManuallyTerminated);
+/// Fuzzy matches a pattern against a name, as a subsequence of its characters (ignoring the
+/// case), e.g. "frst" matches "forest_007.map".
+/// Consecutive characters and characters at the start of a word (after '_', '-', '.' or '/')
+/// score higher.
+///
+/// \param pattern: characters to find, in order.
+/// \param name: name to match.
+/// \return: the score and the char positions of the matched characters in the name, or None if
+///          the name does not contain the pattern.
+///
+pub fn fuzzy_match(pattern: &str, name: &str) -> Option<(u32, Vec<usize>)> {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.chars().flat_map(|c| c.to_lowercase()).collect();
+    let first: char = *pattern.first()?;
+    let mut best: Option<(u32, Vec<usize>)> = None;
+    // Greedy match from every occurrence of the first character, keeping the best one.
+    for start in (0..name.len()).filter(|&i| name[i] == first) {
+        let mut positions: Vec<usize> = vec![start];
+        let mut next: usize = start + 1;
+        for &c in pattern[1..].iter() {
+            match name[next..].iter().position(|&n| n == c) {
+                Some(offset) => {
+                    positions.push(next + offset);
+                    next += offset + 1;
                 },
-                Err(err) => {
-                    println!("FILE MNG :: ERROR :: failed due to {err}");
-                }
+                None => break,
             }
         }
+        if positions.len() < pattern.len() {
+            break; // Later starts cannot match either.
+        }
+        let score: u32 = positions
+            .iter()
+            .enumerate()
+            .map(|(cnt, &pos)| {
+                let consecutive: bool = cnt > 0 && positions[cnt - 1] + 1 == pos;
+                let word_start: bool = pos == 0 || matches!(name[pos - 1], '_' | '-' | '.' | '/');
+                1 + if consecutive { 4 } else { 0 } + if word_start { 3 } else { 0 }
+            })
+            .sum();
+        if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+            best = Some((score, positions));
+        }
     }
+    best
 }
 
-/// Intermediate function to handle errors in the save file menu.
-/// If is_saving is true, it will run the file saving option; otherwise it will run the load
-/// file option.
+/// Ranks the entries matching a fuzzy pattern, best first (ties keep the listing order).
 ///
-fn run_save_file_menu_with_errors(is_saving: bool, default_dirs: Option<Vec<String>>) -> Result<String> {
-    let (default_path, paths) = init_default_paths(default_dirs);
-    let full_path: String = file_name_menu(default_path, &paths, is_saving)?;
-    Ok(full_path)
+/// Returns: the listing number of each matching entry and its matched char positions.
+fn rank_fuzzy_matches(pattern: &str, entries: &[ListingEntry]) -> Vec<(usize, Vec<usize>)> {
+    let mut matches: Vec<(u32, usize, Vec<usize>)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(num, entry)| {
+            let (ListingEntry::Dir(name) | ListingEntry::File(name)) = entry;
+            fuzzy_match(pattern, name).map(|(score, positions)| (score, num, positions))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.into_iter().map(|(_, num, positions)| (num, positions)).collect()
+}
+
+/// Highlights (in bold) the characters of a name at the given char positions.
+pub(crate) fn highlight_chars(name: &str, positions: &[usize]) -> String {
+    name.chars()
+        .enumerate()
+        .map(|(pos, c)| {
+            if positions.contains(&pos) { format!("\x1b[1m{}\x1b[22m", c) } else { c.to_string() }
+        })
+        .collect()
+}
+
+/// Opens the dir in the file explorer of the OS. Failures are only warned, e.g. when there is no
+/// desktop environment.
+#[cfg(feature = "reveal")]
+fn reveal_dir(dir: &Path, messages: &dyn Messages) {
+    let opener: &str = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    match std::process::Command::new(opener).arg(dir).status() {
+        // explorer exits with 1 even when the dir is opened.
+        Ok(status) if status.success() || cfg!(windows) => (),
+        Ok(status) => println!("{}", messages.reveal_failed(opener, dir, &status.to_string())),
+        Err(e) => println!("{}", messages.reveal_failed(opener, dir, &e.to_string())),
+    }
 }
 
 // ----------------------------------------
-// Mains
+// Menus
 // ----------------------------------------
 
-/// Runs the file naming menu.
-///
-/// All errors are handled internally for simplicity.
-///
-/// \param is_saving: if true serves the file save menu; otherwise it serves the load file menu.
-/// \return: option with the selected file name, None if error took place or it was canceled.
-///
-pub fn run_file_naming_menu(is_saving: bool, default_dirs: Option<Vec<String>>) -> Option<String> {
-    match run_save_file_menu_with_errors(is_saving, default_dirs) {
-        Err(e) => {
-            println!("{e}");
-            None
+fn print_help_banner(out: &mut dyn io::Write, mode: MenuMode, messages: &dyn Messages)
+    -> io::Result<()> {
+    writeln!(out, "{}", messages.help_banner(mode))
+}
+
+fn print_menu_options(out: &mut dyn io::Write, current_dir: &str, paths: &[String],
+                      listing: &MenuFileList, mode: MenuMode, config: &FileManagerConfig)
+    -> io::Result<()> {
+    let messages: &dyn Messages = config.messages.as_ref();
+    if config.show_help_banner {
+        print_help_banner(out, mode, messages)?;
+    } else {
+        writeln!(out, "{}", messages.short_help())?;
+    }
+    let filter_note: String = match listing.filter {
+        SeriesFilter::All => String::new(),
+        SeriesFilter::Series => messages.filter_note_series(),
+        SeriesFilter::Single => messages.filter_note_single(),
+        SeriesFilter::Latest => messages.filter_note_latest(),
+    };
+    if config.listing_layout == ListingLayout::Merged {
+        // Same numbers as the sections, in a single list.
+        writeln!(out, "----\n{}", messages.current_dir_header(&format_breadcrumb(current_dir)))?;
+        let entries: Vec<String> = paths
+            .iter()
+            .chain(listing.sub_dirs.iter())
+            .cloned()
+            .chain(get_listed_names(&listing.files, mode, config))
+            .chain(get_listed_names(&listing.recent, mode, config))
+            .collect();
+        let count: usize = entries.len() + listing.omitted;
+        writeln!(out, "----\n{}", messages.entries_header(count, &filter_note))?;
+        print_option_list(out, &entries, &config.empty_files_note, 0, config)?;
+        // The dirs are still listed, e.g. when the files cannot be read or are filtered out.
+        if let (true, Some(empty_note)) = (listing.files.is_empty(), &listing.empty_note) {
+            writeln!(out, "{}", empty_note)?;
+        }
+        if listing.omitted > 0 {
+            writeln!(out, "{}", messages.omitted_files(listing.omitted))?;
         }
-        Ok(s) => Some(s)
+        return Ok(());
     }
+    writeln!(out, "----\n{}", messages.default_dirs_header(paths.len()))?;
+    print_paths(out, paths, 0, config)?;
+    writeln!(out, "----\n{}", messages.current_dir_header(&format_breadcrumb(current_dir)))?;
+    writeln!(out, "----\n{}", messages.sub_dirs_header(listing.sub_dirs.len()))?;
+    print_option_list(out, &listing.sub_dirs, &config.empty_dirs_note, paths.len(), config)?;
+    writeln!(out, "----\n{}", messages.files_header(listing.files.len() + listing.omitted,
+                                                     &filter_note))?;
+    let start: usize = paths.len() + listing.sub_dirs.len();
+    print_dir_files(out, listing, start, mode, config)?;
+    if !listing.recent.is_empty() {
+        writeln!(out, "----\n{}", messages.recent_header(listing.recent.len()))?;
+        print_option_list(out, &get_listed_names(&listing.recent, mode, config), "",
+                          start + listing.files.len(), config)?;
+    }
+    Ok(())
 }
 
-/// Creates a test file to test the crate.
-pub fn create_test_file(file_path: String) {
-    match fs::write(file_path, "This is just a test file, please delete.") {
-        Ok(_) => println!("File created!"),
-        Err(e) => println!("Failed to crate file {e}"),
+/// Formats a dir as a breadcrumb of its components, e.g. "test_dir › maps › forest".
+/// The leading "." of a relative dir is dropped, unless it is the whole dir.
+fn format_breadcrumb(dir: &str) -> String {
+    let components: Vec<Cow<str>> = Path::new(dir)
+        .components()
+        .skip_while(|component| component == &Component::CurDir)
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    if components.is_empty() {
+        return ".".to_string();
+    }
+    components.join(BREADCRUMB_SEPARATOR)
+}
+
+/// Checks that the name has at most max_name_chars characters (not bytes, so that non-ASCII names
+/// get the same limit).
+fn check_file_name_len(name: &str, config: &FileManagerConfig) -> Result<()> {
+    if name.chars().count() > config.max_name_chars {
+        Err(Error::InvalidNameTooLong(config.max_name_chars))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that the name is not empty and that it has no characters that are invalid in common
+/// file systems.
+fn check_file_name_chars(name: &str) -> Result<()> {
+    if name.is_empty() || name.chars().any(|c| c.is_control() || "<>:\"/\\|?*".contains(c)) {
+        Err(Error::InvalidNameChars)
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that a file name has an accepted extension, the same rule for the menu and the
+/// functions without menu: the configured extension or one of allowed_extra_extensions. Names
+/// without extension are only accepted if require_extension is not set.
+fn check_file_extension(file_name: &str, config: &FileManagerConfig) -> Result<()> {
+    match Path::new(file_name).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext == config.extension ||
+            config.allowed_extra_extensions.iter().any(|extra| extra == ext) => Ok(()),
+        None if !config.require_extension => Ok(()),
+        _ => Err(Error::UnknownFileType(config.extension.clone())),
+    }
+}
+
+/// Checks the name and extension of a file path.
+///
+/// Returns: the directory of the file ("." if it has none) and the file name.
+fn check_file_path<'p>(path: &'p Path, config: &FileManagerConfig) -> Result<(&'p Path, &'p str)> {
+    let file_name: &str = match path.file_name().and_then(|name| name.to_str()) {
+        Some(file_name) => file_name,
+        None => return Err(Error::InvalidNameChars),
+    };
+    check_file_name_chars(file_name)?;
+    check_file_name_len(file_name, config)?;
+    check_file_extension(file_name, config)?;
+    let parent: &Path = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Ok((parent, file_name))
+}
+
+/// Checks that a file is inside one of the roots. Its dir and the roots must exist to be compared.
+///
+/// Returns: Error::OutsideRoot with the resolved path of the file and the roots, if it is not.
+fn check_inside_roots(dir: &Path, file_name: &str, roots: &[String]) -> Result<()> {
+    let dir: PathBuf = dir.canonicalize()?;
+    if roots.iter()
+        .filter_map(|root| Path::new(root).canonicalize().ok())
+        .any(|root| dir.starts_with(root)) {
+        Ok(())
+    } else {
+        let roots: Vec<PathBuf> = roots.iter().map(PathBuf::from).collect();
+        Err(Error::OutsideRoot { path: dir.join(file_name), roots })
+    }
+}
+
+/// Resolves a path that may not exist yet into an absolute one: the deepest existing ancestor is
+/// canonicalized (following symlinks) and the missing components are appended lexically.
+fn resolve_missing_path(path: &Path) -> Result<PathBuf> {
+    let path: PathBuf = std::env::current_dir()?.join(path);
+    let existing: &Path = path.ancestors().find(|dir| dir.is_dir()).unwrap_or(Path::new("/"));
+    let mut resolved: PathBuf = existing.canonicalize()?;
+    for component in path.strip_prefix(existing).unwrap_or(Path::new("")).components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            },
+            Component::Normal(name) => resolved.push(name),
+            _ => (),
+        }
+    }
+    Ok(resolved)
+}
+
+/// Checks that a file to be saved is inside one of the allowed roots, if any. The dir does not
+/// need to exist.
+///
+/// Returns: Error::OutsideRoot with the resolved path of the file and the roots, if it is not.
+fn check_allowed_save_path(dir: &str, file_name: &str, config: &FileManagerConfig) -> Result<()> {
+    match check_allowed_dir(dir, config) {
+        Err(Error::OutsideRoot { path, roots }) =>
+            Err(Error::OutsideRoot { path: path.join(file_name), roots }),
+        result => result,
+    }
+}
+
+/// Checks that a dir is inside one of the allowed roots, if any. The dir does not need to exist.
+///
+/// Returns: Error::OutsideRoot with the resolved path of the dir and the roots, if it is not.
+fn check_allowed_dir(path: &str, config: &FileManagerConfig) -> Result<()> {
+    if config.allowed_roots.is_empty() {
+        return Ok(());
+    }
+    let path: PathBuf = resolve_missing_path(Path::new(path))?;
+    if config.allowed_roots.iter()
+        .filter_map(|root| config.root.join(root).canonicalize().ok())
+        .any(|root| path.starts_with(root)) {
+        Ok(())
+    } else {
+        let roots: Vec<PathBuf> =
+            config.allowed_roots.iter().map(|root| config.root.join(root)).collect();
+        Err(Error::OutsideRoot { path, roots })
+    }
+}
+
+/// Applies a conflict action to an existing file, without any user interaction.
+///
+/// \param action: action to apply.
+/// \param path: directory of the file.
+/// \param file_name: name of the existing file.
+/// \param mode: conflicts only take place when saving, when loading the name is kept.
+/// \return: the name to use for the new file. Error::NeedNewName if a new name must be selected,
+///          Error::FileDeletion if the existing file has been deleted, and Error::FileExists for
+///          ConflictAction::Fail.
+///
+pub fn apply_conflict_action(action: ConflictAction, path: &str, file_name: String, mode: MenuMode,
+                             config: &FileManagerConfig) -> Result<String> {
+    let full_path: PathBuf = Path::new(path).join(&file_name);
+    if mode == MenuMode::Load {
+        return Ok(file_name);
+    }
+    if config.read_only && action.modifies_disk() {
+        return Err(Error::ReadOnly);
+    }
+    match action {
+        ConflictAction::Replace => {
+            println!("{}", config.messages.replacing(&full_path));
+            for (sidecar, _) in get_sidecars(Path::new(path), &file_name, config) {
+                fs::remove_file(Path::new(path).join(sidecar))?;
+            }
+            fs::remove_file(full_path)?;
+            Ok(file_name)
+        },
+        ConflictAction::MoveExisting => {
+            let new_name: String = get_next_sequential_name(Path::new(path), &file_name, config)?;
+            let new_path: PathBuf = Path::new(path).join(&new_name);
+            println!("{}", config.messages.renaming(&full_path, &new_path.to_string_lossy()));
+            for (sidecar, ext) in get_sidecars(Path::new(path), &file_name, config) {
+                fs::rename(Path::new(path).join(sidecar), sidecar_path(&new_path, &ext))?;
+            }
+            fs::rename(full_path, new_path)?;
+            Ok(file_name)
+        },
+        ConflictAction::SequentialNew => get_next_sequential_name(Path::new(path), &file_name, config),
+        ConflictAction::NewName => Err(Error::NeedNewName),
+        ConflictAction::Delete => {
+            let trash_path: PathBuf = move_to_trash_with_sidecars(Path::new(path), &file_name, config)?;
+            println!("{}", config.messages.moved_to_trash(&full_path, &trash_path));
+            Err(Error::FileDeletion)
+        },
+        ConflictAction::Fail => Err(Error::FileExists(full_path)),
+    }
+}
+
+/// Checks if the specified directory exists or not.
+fn check_dir_exists(path: &str) -> bool {
+    Path::new(path).is_dir()
+}
+
+
+/// Gets the directories that need to be created for the path, from the outermost one.
+fn get_missing_dirs(path: &Path) -> Vec<PathBuf> {
+    let mut missing_dirs: Vec<PathBuf> = path
+        .ancestors()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .take_while(|dir| !dir.is_dir())
+        .map(Path::to_path_buf)
+        .collect();
+    missing_dirs.reverse();
+    missing_dirs
+}
+
+/// Renames all the files of the dir, or none: if a new name already exists or a rename fails,
+/// the previous renames are undone.
+fn rename_all(dir: &Path, renames: &[(String, String)], messages: &dyn Messages) -> Result<()> {
+    for (done, (old_name, new_name)) in renames.iter().enumerate() {
+        let result: Result<()> = if dir.join(new_name).exists() {
+            Err(Error::SeriesTargetExists(new_name.clone()))
+        } else {
+            fs::rename(dir.join(old_name), dir.join(new_name)).map_err(Error::from)
+        };
+        if let Err(e) = result {
+            for (old_name, new_name) in renames[..done].iter().rev() {
+                if let Err(undo_e) = fs::rename(dir.join(new_name), dir.join(old_name)) {
+                    println!("{}", messages.restore_failed(old_name, &Error::from(undo_e)));
+                }
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Renames all the files of the dir, or none, through temporary names first so that the new names
+/// may be the old names of other renamed files, e.g. when renumbering a series.
+fn rename_all_via_temp(dir: &Path, renames: &[(String, String)], messages: &dyn Messages)
+    -> Result<()> {
+    let to_temp: Vec<(String, String)> = renames
+        .iter()
+        .map(|(old_name, _)| (old_name.clone(), format!(".{}{}", old_name, RENAME_TEMP_SUFFIX)))
+        .collect();
+    rename_all(dir, &to_temp, messages)?;
+    let from_temp: Vec<(String, String)> = to_temp
+        .iter()
+        .zip(renames.iter())
+        .map(|((_, temp_name), (_, new_name))| (temp_name.clone(), new_name.clone()))
+        .collect();
+    if let Err(e) = rename_all(dir, &from_temp, messages) {
+        for (old_name, temp_name) in to_temp.iter() {
+            if let Err(undo_e) = fs::rename(dir.join(temp_name), dir.join(old_name)) {
+                println!("{}", messages.restore_failed(old_name, &Error::from(undo_e)));
+            }
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Gets the total size of the managed files of the dir.
+fn get_dir_size(dir: &Path, config: &FileManagerConfig) -> Result<u64> {
+    let entries: FileEntries = get_file_entries_limited(dir, usize::MAX, false, config)?;
+    Ok(entries.files.iter().filter_map(|(_, metadata)| Some(metadata.as_ref()?.len())).sum())
+}
+
+/// Finds a listed file of the dir with the same content.
+fn find_identical_file(dir: &Path, content: &[u8], config: &FileManagerConfig)
+    -> Result<Option<String>> {
+    let entries: FileEntries = get_file_entries_limited(dir, usize::MAX, false, config)?;
+    for (name, metadata) in entries.files {
+        let same_len: bool = metadata.is_none_or(|metadata| metadata.len() == content.len() as u64);
+        if same_len && fs::read(dir.join(&name))? == content {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
+
+/// Normalizes a typed directory, collapsing duplicated separators and removing "." segments.
+/// ".." segments are kept as they are. The result always ends with '/'.
+fn normalize_dir(dir: &str) -> String {
+    let root: &str = if dir.starts_with('/') { "/" } else { "" };
+    let segments: Vec<&str> = dir
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+    if segments.is_empty() {
+        if root.is_empty() { "./".to_string() } else { root.to_string() }
+    } else {
+        format!("{}{}/", root, segments.join("/"))
+    }
+}
+
+/// Resolves the ".." segments of a dir lexically, each one removes the previous segment, e.g.
+/// "maps/forest/../desert/" -> "maps/desert/". The ".." segments at the beginning of a relative
+/// dir are kept, and at the filesystem root they are dropped. The result always ends with '/'.
+fn resolve_parent_segments(dir: &str) -> String {
+    let root: &str = if dir.starts_with('/') { "/" } else { "" };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in dir.split('/').filter(|segment| !segment.is_empty() && *segment != ".") {
+        if segment != ".." {
+            segments.push(segment);
+        } else if segments.last().is_some_and(|last| *last != "..") {
+            segments.pop();
+        } else if root.is_empty() {
+            segments.push(segment);
+        }
+    }
+    normalize_dir(&format!("{}{}/", root, segments.join("/")))
+}
+
+/// Resolves a typed dir: a dir starting with ".." is relative to the current dir, e.g. "../maps/"
+/// is a sibling dir, and other relative dirs are relative to the root. The ".." segments are
+/// resolved lexically, so that they do not depend on how the OS handles them (e.g. symlinks).
+fn resolve_typed_dir(dir: &str, current_path: &str, config: &FileManagerConfig) -> String {
+    let dir: String = if dir == ".." || dir.starts_with("../") {
+        format!("{}{}", current_path, dir)
+    } else {
+        resolve_from_root(dir, config)
+    };
+    resolve_parent_segments(&dir)
+}
+
+/// Checks if an input is a path, a filename or both.
+///
+/// param line: User input.
+///
+/// Return tuple:
+///     - Path option: if none the dir has not been changed.
+///     - Path option: if none there is no valid file name.
+fn check_if_path_or_file(line: &str) -> (Option<String>, Option<String>) {
+    if line.is_empty() {
+        (None, None)
+    } else if line.ends_with('/') { // Only directory.
+        (Some(normalize_dir(line)), None)
+    } else {
+        match line.rsplit_once('/') {
+            Some((d, f)) => (Some(normalize_dir(&format!("{d}/"))), Some(f.to_string())), // Is path with directory.
+            None => (None, Some(line.to_string())), // Only file.
+        }
+    }
+}
+
+/// Gets the listed files whose name starts with the typed prefix, e.g. "for" for
+/// "forest_003.map". The names are matched as listed in load mode, without the name template.
+///
+/// Returns: the listing numbers of the files with the char positions of the prefix, as the fuzzy
+///          matches. Empty if the input is not a plain name or it is the full name of a file.
+fn get_prefix_matches(line: &str, entries: &[ListingEntry], config: &FileManagerConfig)
+    -> Vec<(usize, Vec<usize>)> {
+    if line.is_empty() || line.contains('/') || line.ends_with(config.sequential_separator) ||
+        line.starts_with(LITERAL_NAME_CHAR) {
+        return Vec::new();
+    }
+    let mut matches: Vec<(usize, Vec<usize>)> = Vec::new();
+    for (num, entry) in entries.iter().enumerate() {
+        let ListingEntry::File(file) = entry else {
+            continue;
+        };
+        // The prefix is matched after the template prefix, e.g. "proj42_" of "proj42_{name}".
+        let (name, start): (String, usize) = match strip_name_template(file, config) {
+            Some(name) => (name, get_name_template_parts(config).0.chars().count()),
+            None => (file.clone(), 0),
+        };
+        if name == line || name.split_once('.').is_some_and(|(stem, _)| stem == line) {
+            return Vec::new(); // The full name is resolved as typed.
+        }
+        if name.starts_with(line) {
+            matches.push((num, (start..start + line.chars().count()).collect()));
+        }
+    }
+    matches
+}
+
+/// Parses the menu inputs. Numbers are resolved against the entries of the rendered listing, and
+/// in load mode a name that is the prefix of only one listed file selects that file. A leading
+/// '=' takes the rest of the input as a name, e.g. "=2024" for the file "2024.map".
+/// Outputs:
+///     - Path option: if none the dir has not been changed.
+///     - Path option: if none there is no valid file name.
+fn parse_menu_file(line: &str, entries: &[ListingEntry], current_path: &str, mode: MenuMode,
+                   config: &FileManagerConfig) -> Result<(Option<String>, Option<String>)> {
+    let path: Option<String>;
+    let file_name: Option<String>;
+
+    if line.is_empty() {
+        // Empty input -> return
+        println!("{}", config.messages.empty_input());
+        file_name = None;
+        path = None;
+    } else if let Ok(num) = line.parse::<usize>() {
+        // Number input --> load existing name.
+        match entries.get(num) {
+            Some(ListingEntry::Dir(dir)) => {
+                path = Some(dir.clone());
+                file_name = None;
+            },
+            Some(ListingEntry::File(file)) => {
+                path = None;
+                file_name = Some(is_sequential_name(file, config));
+            },
+            None => {
+                path = None;
+                file_name = None;
+                println!("{}", config.messages.out_of_range(&num.to_string()));
+                println!("{}", config.messages.literal_name_hint(line));
+            },
+        }
+    } else if let Some(name) = line.strip_prefix(LITERAL_NAME_CHAR)
+        .filter(|name| !name.is_empty()) {
+        // Escaped name --> taken as typed, e.g. a name made of digits.
+        let (typed_path, typed_file) = check_if_path_or_file(name);
+        path = typed_path.map(|typed_path| resolve_typed_dir(&typed_path, current_path, config));
+        file_name = typed_file;
+    } else if let Some(file) = get_unique_prefix_match(line, entries, mode, config) {
+        // Unique prefix --> load the matching file.
+        path = None;
+        file_name = Some(file);
+    } else {
+        // Path and/or file name.
+        let (typed_path, typed_file) = check_if_path_or_file(line);
+        path = typed_path.map(|typed_path| resolve_typed_dir(&typed_path, current_path, config));
+        file_name = typed_file;
+    }
+    Ok((path, file_name))
+}
+
+/// Gets the only listed file whose name starts with the typed prefix, load mode only.
+fn get_unique_prefix_match(line: &str, entries: &[ListingEntry], mode: MenuMode,
+                           config: &FileManagerConfig) -> Option<String> {
+    if mode != MenuMode::Load {
+        return None; // A new name may be the prefix of an existing one.
+    }
+    match get_prefix_matches(line, entries, config)[..] {
+        [(num, _)] => match entries.get(num) {
+            Some(ListingEntry::File(file)) => Some(file.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Gets the parts of the name template: the prefix and suffix around {name}, and whether it ends
+/// with {counter}. The separator before {counter} belongs to the sequential name, not to the
+/// suffix.
+fn get_name_template_parts(config: &FileManagerConfig) -> (&str, &str, bool) {
+    let template: &str = &config.name_template;
+    let (template, has_counter): (&str, bool) = match template.strip_suffix("{counter}") {
+        Some(template) => (template, true),
+        None => (template, false),
+    };
+    let (prefix, suffix): (&str, &str) = template.split_once("{name}").unwrap_or(("", ""));
+    let suffix: &str = if has_counter {
+        suffix.strip_suffix(config.sequential_separator).unwrap_or(suffix)
+    } else {
+        suffix
+    };
+    (prefix, suffix, has_counter)
+}
+
+/// Splits a file name into the part the name template applies to, and the sequential suffix and
+/// extension, e.g. "forest_004.map" -> ("forest", "_004.map"), "forest_" -> ("forest", "_") or
+/// "forest.map" -> ("forest", ".map").
+///
+/// Returns: the two parts, and whether the name is sequential.
+fn split_template_core<'a>(file: &'a str, config: &FileManagerConfig) -> (&'a str, &'a str, bool) {
+    let base_name: String = is_sequential_name(file, config);
+    let end: usize = if base_name != file {
+        base_name.len() - 1
+    } else if let Some(core) = file.strip_suffix(config.sequential_separator) {
+        core.len() // Typed sequential name, without counter yet.
+    } else {
+        file.find('.').unwrap_or(file.len())
+    };
+    (&file[..end], &file[end..], file[end..].starts_with(config.sequential_separator))
+}
+
+/// Strips the name template from a file name, e.g. "proj42_forest_004.map" -> "forest_004.map"
+/// for "proj42_{name}". The sequential suffix and the extension are kept.
+///
+/// Returns: the name without the template, None if the name does not follow it.
+fn strip_name_template(file: &str, config: &FileManagerConfig) -> Option<String> {
+    let (prefix, suffix, has_counter) = get_name_template_parts(config);
+    let (core, tail, is_sequential) = split_template_core(file, config);
+    if has_counter && !is_sequential {
+        return None; // With {counter} all the names are sequential.
+    }
+    let name: &str = core.strip_prefix(prefix)?.strip_suffix(suffix)?;
+    if name.is_empty() {
+        return None;
+    }
+    Some(format!("{name}{tail}"))
+}
+
+/// Applies the name template to a typed name, e.g. "forest" -> "proj42_forest" for
+/// "proj42_{name}", or "forest" -> "forest_" (sequential) for "{name}_{counter}". Names that
+/// already follow the template are kept.
+fn apply_name_template(file: &str, config: &FileManagerConfig) -> String {
+    let (core, tail, is_sequential) = split_template_core(file, config);
+    if core.is_empty() || strip_name_template(file, config).is_some() {
+        return file.to_string(); // An empty name is left to the name checks.
+    }
+    let (prefix, suffix, has_counter) = get_name_template_parts(config);
+    // The extension is added back after the sequential naming.
+    let separator: String = config.sequential_separator.to_string();
+    let tail: &str = if has_counter && !is_sequential { &separator } else { tail };
+    format!("{prefix}{core}{suffix}{tail}")
+}
+
+/// Resolves a typed file name as the menu does: the name template is applied, a sequential name
+/// (ending in '_') gets its counter, and the extension is checked or added if missing (or empty,
+/// e.g. "name."). Names without extension are kept if require_extension is not set.
+///
+/// Returns: the file name and its counter if it was sequential. Error::InvalidNameChars if the name
+///          is empty, Error::UnknownFileType if the extension is not accepted, and
+///          Error::InvalidNameTooLong if the resolved name is too long.
+fn resolve_file_name(dir: &Path, file: &str, mode: MenuMode, config: &FileManagerConfig)
+    -> Result<(String, Option<u16>)> {
+    let file: &str = &apply_name_template(file, config);
+    // check sequential naming
+    let (file, cnt): (String, Option<u16>) = if file.ends_with(config.sequential_separator) {
+        let base_name: String = get_sequential_base_name(file, config);
+        let (name, cnt): (String, u16) =
+            get_sequential_name_and_count(dir, &base_name, mode == MenuMode::Save, config)?;
+        (name, Some(cnt))
+    } else {
+        (file.to_string(), None)
+    };
+    // Check extension
+    if file.starts_with('.') {
+        return Err(Error::InvalidNameChars);
+    }
+    // Legacy file without extension, see include_extensionless.
+    let legacy: bool = mode == MenuMode::Load && config.include_extensionless &&
+        Path::new(&file).extension().is_none() && dir.join(&file).is_file();
+    let file: String = match Path::new(&file).extension() {
+        Some(ext) if ext.is_empty() => format!("{}{}", file, config.extension), // e.g. "name.".
+        None if config.require_extension && !legacy => format!("{}.{}", file, config.extension),
+        _ => file,
+    };
+    if !legacy {
+        check_file_extension(&file, config)?;
+    }
+    // Name length
+    check_file_name_len(&file, config)?;
+    Ok((file, cnt))
+}
+
+// ----------------------------------------
+// Menu state machine
+// ----------------------------------------
+
+/// Result of feeding an input to the `FileNameMenu`.
+#[derive(Debug)]
+pub enum MenuStep {
+    /// The prompt has changed (e.g. new dir or a pending confirmation), `render()` must be called
+    /// before reading the next input.
+    Refresh,
+    /// The input has been handled, the next one is read with the same prompt.
+    NeedMoreInput,
+    /// A file has been selected, it holds its full path.
+    Selected(String),
+    /// The menu has been cancelled.
+    Cancelled,
+    /// The menu cannot continue.
+    Failed(Error),
+}
+
+/// Outcome of running the interactive menu, see `run_file_menu_with_outcome`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuOutcome {
+    /// A file has been selected, with its full path and its counter if it is sequential (see
+    /// `FileNameMenu::sequential_number`).
+    Selected { path: String, sequential_number: Option<u16> },
+    /// The menu has been cancelled (CTRL+D or input_timeout), it holds the dir it was in.
+    Cancelled(String),
+}
+
+/// Confirmations asked by the menu, answered with yes or no.
+enum Confirmation {
+    /// Create the missing dir, then select the typed file if any.
+    CreateDir { path: String, file: Option<String> },
+    /// Delete (move to the trash) the selected files.
+    Delete(Vec<String>),
+    /// Save the file although the dir is over its size budget.
+    OverBudget { file: String, size: u64, budget: u64 },
+    /// Rename the files of a series, (old name, new name) pairs.
+    RenameSeries(Vec<(String, String)>),
+    /// Renumber the files of a series, (old name, new name) pairs.
+    Renumber(Vec<(String, String)>),
+    /// Select an existing file with the same content instead of saving the new file.
+    UseExisting { existing: String, file: String },
+    /// Rename the existing file to its next sequential name, then save the new file with its name.
+    MoveExisting { file: String, new_name: String },
+    /// Use the final name, once every check has passed. A conflict action that changes the
+    /// existing file is applied once confirmed, and a claimed sequential name is removed if
+    /// declined.
+    Final { file: String, action: Option<ConflictAction>, claimed: bool },
+}
+
+/// Input expected by the menu.
+enum MenuState {
+    /// A file name, a dir, a number or a command.
+    Listing,
+    /// A conflict action for a file that already exists while saving.
+    Conflict(String),
+    /// Yes or no.
+    Confirm(Confirmation),
+    /// Number of a file found in the default dirs, (dir, file) pairs.
+    Search(Vec<(String, String)>),
+    /// Number of an entry matching a fuzzy pattern, (listing number, matched chars) pairs.
+    Fuzzy(String, Vec<(usize, Vec<usize>)>),
+}
+
+/// File name selection menu as a state machine fed with the user inputs.
+/// It never reads the input itself, so it can be driven from any event source: call `render()`
+/// to print the current prompt and `handle_input()` with every input line, until a file is
+/// selected or the menu is cancelled. `run_file_menu` drives it with rustyline.
+///
+/// If is saving is:
+///     True:
+///         - Sequential naming will yield the next unused name.
+///         - If the selected name already exists the conflict actions are asked.
+///     False: thus, is loading a file.
+///         - Sequential naming will yield the last used name.
+///         - The selected name must exist.
+pub struct FileNameMenu<'a> {
+    /// Configuration given to the menu.
+    base_config: &'a FileManagerConfig,
+    /// Configuration of the current dir, with its overrides if dir_overrides is set.
+    config: Cow<'a, FileManagerConfig>,
+    mode: MenuMode,
+    paths: Vec<String>,
+    current_path: String,
+    listing: MenuFileList,
+    /// Numbered entries as rendered. Numbers are resolved against this snapshot, so that changes
+    /// on disk after rendering do not shift them.
+    numbered: Vec<ListingEntry>,
+    init_s: String,
+    state: MenuState,
+    /// Content to be saved, to detect identical files.
+    content: Option<Vec<u8>>,
+    series_filter: SeriesFilter,
+    /// Counter of the last name resolved from a sequential name.
+    sequential_number: Option<u16>,
+    /// Dir of the pending save when current_path is its session dir, which is not created until
+    /// the save is selected.
+    session_parent: Option<String>,
+}
+
+impl<'a> FileNameMenu<'a> {
+    /// Creates the menu in the default dir (or the last used one if remember_last_dir is set).
+    ///
+    /// \param mode: serves the file save or the file load menu.
+    /// \param default_dirs: default dirs to list, if None the default dir is used.
+    /// \param config: file manager configuration.
+    /// \return: the menu, or an error if the configuration is invalid or the dir cannot be read.
+    ///
+    pub fn new(mode: MenuMode, default_dirs: Option<Vec<String>>, config: &'a FileManagerConfig)
+        -> Result<Self> {
+        config.validate()?;
+        let (default_path, paths) = init_default_paths(default_dirs, config);
+        // Load mode never writes to the listed dirs, so that read-only mounts can be browsed.
+        if config.create_missing_defaults && !config.read_only && mode == MenuMode::Save {
+            ensure_default_paths(&paths, config)?;
+        }
+        // The saves of a session start in its dir (or in the default dir until the first save
+        // creates it), never in the remembered dir of a previous run.
+        let start_dir: Option<String> = if is_session_save(mode, config) {
+            get_session_dir(&default_path)
+        } else if config.remember_last_dir {
+            load_last_dir(config)
+        } else {
+            None
+        };
+        let current_path: String = start_dir
+            .filter(|dir| check_allowed_dir(dir, config).is_ok())
+            .unwrap_or(default_path);
+        check_allowed_dir(&current_path, config)?;
+        let base_config: &FileManagerConfig = config;
+        let config: Cow<FileManagerConfig> = get_dir_config(&current_path, base_config);
+        let listing: MenuFileList = get_menu_file_list(Path::new(&current_path), mode, &config)?;
+        let numbered: Vec<ListingEntry> = get_listing_entries(&paths, &current_path, &listing);
+        Ok(FileNameMenu {
+            base_config,
+            config,
+            mode,
+            paths,
+            current_path,
+            listing,
+            numbered,
+            init_s: String::new(),
+            state: MenuState::Listing,
+            content: None,
+            series_filter: SeriesFilter::All,
+            sequential_number: None,
+            session_parent: None,
+        })
+    }
+
+    /// Sets the content to be saved. When saving, if a file of the selected dir has the same
+    /// content, using it instead of saving a duplicate is offered.
+    pub fn with_content(mut self, content: impl Into<Vec<u8>>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+
+    /// Current dir, ending with '/'.
+    pub fn current_dir(&self) -> &str {
+        &self.current_path
+    }
+
+    /// Path of a file of the current dir.
+    fn full_path(&self, file: &str) -> String {
+        Path::new(&self.current_path).join(file).to_string_lossy().into_owned()
+    }
+
+    /// Default dirs, numbered from 0.
+    pub fn default_dirs(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Sub dirs listed in the current dir, numbered after the default dirs. The parent dir ("../")
+    /// goes first, except at the filesystem root.
+    pub fn sub_dirs(&self) -> &[String] {
+        &self.listing.sub_dirs
+    }
+
+    /// Files listed in the current dir, numbered after the default dirs and the sub dirs.
+    pub fn files(&self) -> &[String] {
+        &self.listing.files
+    }
+
+    /// Counter of the selected name when it was resolved from a sequential name (e.g. "test_"),
+    /// so that it can be reported as "version 7" without parsing the name.
+    pub fn sequential_number(&self) -> Option<u16> {
+        self.sequential_number
+    }
+
+    /// Text the next input should start with, e.g. a name with the extension fixed.
+    pub fn initial_input(&self) -> &str {
+        &self.init_s
+    }
+
+    /// Entries that can be selected by number: the default dirs, the sub dirs and the files.
+    /// Empty while a conflict or a confirmation is pending.
+    pub fn entries(&self) -> Vec<String> {
+        match self.state {
+            MenuState::Listing => self.numbered
+                .iter()
+                .map(|entry| match entry {
+                    ListingEntry::Dir(name) | ListingEntry::File(name) => name.clone(),
+                })
+                .collect(),
+            MenuState::Search(ref results) =>
+                results.iter().map(|(_, file)| file.clone()).collect(),
+            MenuState::Fuzzy(_, ref matches) => matches
+                .iter()
+                .filter_map(|(num, _)| self.numbered.get(*num))
+                .map(|entry| match entry {
+                    ListingEntry::Dir(name) | ListingEntry::File(name) => name.clone(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Suggested inputs for the current prompt, most relevant first (e.g. for the input history).
+    /// In the listing, the extra_history names go first.
+    pub fn suggestions(&self) -> Vec<String> {
+        match self.state {
+            MenuState::Listing => self.config.extra_history
+                .iter()
+                .cloned()
+                .chain(self.entries())
+                .collect(),
+            MenuState::Search(_) | MenuState::Fuzzy(..) => self.entries(),
+            MenuState::Conflict(_) =>
+                ConflictAction::ALL.iter().map(|action| action.key().to_string()).collect(),
+            MenuState::Confirm(_) => ["y", "yes", "n", "no"].map(String::from).to_vec(),
+        }
+    }
+
+    /// Command words valid for the current prompt, e.g. for completion.
+    pub fn commands(&self) -> Vec<String> {
+        match self.state {
+            MenuState::Listing => {
+                let mut commands: Vec<&str> = match self.mode {
+                    MenuMode::Save =>
+                        vec!["find", "diff", "renameseries", "renumber", "filter", "refresh",
+                             "help"],
+                    MenuMode::Load => vec!["peek", "view", "del", "audit", "find", "diff",
+                                           "renameseries", "renumber", "filter", "refresh", "help"],
+                };
+                if cfg!(feature = "reveal") {
+                    commands.push("reveal");
+                }
+                commands.into_iter().map(String::from).collect()
+            },
+            MenuState::Search(_) | MenuState::Fuzzy(..) => Vec::new(),
+            _ => self.suggestions(),
+        }
+    }
+
+    /// Prints the current prompt: the listing, or the options of the pending conflict or
+    /// confirmation.
+    pub fn render(&self) {
+        // As with println, there is nothing to do if stdout cannot be written.
+        let _ = self.render_to(&mut io::stdout());
+    }
+
+    /// Writes the current prompt into out, e.g. to redirect it to stderr or to capture it.
+    pub fn render_to(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        let messages: &dyn Messages = self.config.messages.as_ref();
+        match &self.state {
+            MenuState::Listing => {
+                print_menu_options(out, &self.current_path, &self.paths, &self.listing, self.mode,
+                                   &self.config)?;
+                for name in self.listing.skipped.iter() {
+                    writeln!(out, "{}", messages.skipping_non_utf8(&name.to_string_lossy()))?;
+                }
+            },
+            MenuState::Conflict(file_name) => {
+                let full_path: PathBuf = Path::new(&self.current_path).join(file_name);
+                writeln!(out, "{}", messages.conflict_header(&full_path))?;
+                writeln!(out, "{}", messages.conflict_input_header())?;
+                for action in ConflictAction::ALL {
+                    writeln!(out, "{}", messages.conflict_option(action, &self.config.trash_dir))?;
+                }
+            },
+            MenuState::Search(results) => {
+                writeln!(out, "{}", messages.search_header(results.len()))?;
+                for (num, (dir, file)) in results.iter().enumerate() {
+                    writeln!(out, "    {: >number_width$}: {: <name_width$} {}", num, file, dir,
+                             number_width = self.config.number_width,
+                             name_width = self.config.name_width)?;
+                }
+                writeln!(out, "{}", messages.search_footer())?;
+            },
+            MenuState::Fuzzy(pattern, matches) => {
+                writeln!(out, "{}", messages.fuzzy_header(pattern, matches.len()))?;
+                for (rank, (num, positions)) in matches.iter().enumerate() {
+                    let (ListingEntry::Dir(name) | ListingEntry::File(name)) = &self.numbered[*num];
+                    writeln!(out, "    {: >number_width$}: {}", rank,
+                             highlight_chars(name, positions),
+                             number_width = self.config.number_width)?;
+                }
+                writeln!(out, "{}", messages.fuzzy_footer())?;
+            },
+            MenuState::Confirm(confirmation) => {
+                match confirmation {
+                    Confirmation::CreateDir { path, .. } => {
+                        let missing_dirs: Vec<PathBuf> = get_missing_dirs(Path::new(path));
+                        writeln!(out, "{}", messages.confirm_create_dirs(missing_dirs.len()))?;
+                        if missing_dirs.len() > 1 {
+                            for dir in missing_dirs.iter() {
+                                writeln!(out, "    {}", dir.display())?;
+                            }
+                        }
+                    },
+                    Confirmation::Delete(names) => {
+                        writeln!(out, "{}", messages.confirm_delete(names.len(),
+                                                                    &self.config.trash_dir))?;
+                        for name in names.iter() {
+                            writeln!(out, "    {}", name)?;
+                        }
+                    },
+                    Confirmation::OverBudget { file, size, budget } => {
+                        writeln!(out, "{}", messages.over_budget(&self.current_path, *size,
+                                                                 *budget))?;
+                        writeln!(out, "{}", messages.confirm_save_anyway(file))?;
+                    },
+                    Confirmation::RenameSeries(renames) | Confirmation::Renumber(renames) => {
+                        writeln!(out, "{}", messages.confirm_rename_series(renames.len()))?;
+                        for (old_name, new_name) in renames.iter() {
+                            writeln!(out, "    {} -> {}", old_name, new_name)?;
+                        }
+                    },
+                    Confirmation::UseExisting { existing, file } => {
+                        writeln!(out, "{}", messages.confirm_use_existing(existing, file))?;
+                    },
+                    Confirmation::Final { file, .. } => {
+                        writeln!(out, "{}", messages.confirm_final(&self.full_path(file)))?;
+                    },
+                    Confirmation::MoveExisting { file, new_name } => {
+                        writeln!(out, "{}", messages.confirm_move_existing(
+                            &self.full_path(file), &self.full_path(new_name), file))?;
+                    },
+                }
+                writeln!(out, "{}", messages.yes_no_prompt())?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Handles an input line.
+    ///
+    /// \param line: input line, without the line break.
+    /// \return: the next step of the menu.
+    ///
+    pub fn handle_input(&mut self, line: &str) -> MenuStep {
+        let result: Result<MenuStep> = match std::mem::replace(&mut self.state, MenuState::Listing) {
+            MenuState::Listing => self.handle_listing_input(line),
+            MenuState::Conflict(file_name) => self.handle_conflict_input(line, file_name),
+            MenuState::Search(results) => self.handle_search_input(line, results),
+            MenuState::Fuzzy(pattern, matches) => self.handle_fuzzy_input(line, pattern, matches),
+            MenuState::Confirm(confirmation) => match line.trim() {
+                "y" | "yes" => self.confirm(confirmation, true),
+                "n" | "no" => self.confirm(confirmation, false),
+                _ => {
+                    println!("{}", self.config.messages.invalid_input());
+                    self.state = MenuState::Confirm(confirmation);
+                    Ok(MenuStep::NeedMoreInput)
+                },
+            },
+        };
+        let step: MenuStep = self.settle_session_dir(into_menu_step(result));
+        if let MenuStep::Selected(path) = &step {
+            let hook: &Option<Hook<Path>> = match self.mode {
+                MenuMode::Save => &self.config.on_save,
+                MenuMode::Load => &self.config.on_load,
+            };
+            if let Some(hook) = hook {
+                hook.call(Path::new(path));
+            }
+        }
+        step
+    }
+
+    /// Handles an interrupt (CTRL+C): the input is restarted, and a pending conflict or
+    /// confirmation is declined.
+    pub fn handle_interrupt(&mut self) -> MenuStep {
+        let result: Result<MenuStep> = match std::mem::replace(&mut self.state, MenuState::Listing) {
+            MenuState::Listing => {
+                self.init_s.clear();
+                Ok(MenuStep::NeedMoreInput)
+            },
+            MenuState::Conflict(_) | MenuState::Confirm(Confirmation::UseExisting { .. }) =>
+                self.need_new_name(),
+            MenuState::Search(_) | MenuState::Fuzzy(..) => self.refresh(),
+            MenuState::Confirm(confirmation) => self.confirm(confirmation, false),
+        };
+        self.settle_session_dir(into_menu_step(result))
+    }
+
+    /// Handles the end of the input (CTRL+D): the menu is cancelled.
+    pub fn handle_eof(&mut self) -> MenuStep {
+        self.drop_session_plan();
+        MenuStep::Cancelled
+    }
+
+    /// Reads the listing of the current dir again and goes back to it.
+    fn refresh(&mut self) -> Result<MenuStep> {
+        self.drop_session_plan();
+        self.leave_removed_dir();
+        self.listing = get_menu_file_list(Path::new(&self.current_path), self.mode, &self.config)?;
+        filter_series(&mut self.listing, self.series_filter, &self.config);
+        self.numbered = get_listing_entries(&self.paths, &self.current_path, &self.listing);
+        self.state = MenuState::Listing;
+        Ok(MenuStep::Refresh)
+    }
+
+    /// Moves out of the current dir if it has been removed (e.g. by another process), into its
+    /// closest existing parent, or the first default dir if the parent is not allowed.
+    ///
+    /// Returns: true if the current dir had been removed.
+    fn leave_removed_dir(&mut self) -> bool {
+        if check_dir_exists(&self.current_path) {
+            return false;
+        }
+        let fallback: String = Path::new(&self.current_path)
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.is_dir())
+            .map(|dir| normalize_dir(&dir.to_string_lossy()))
+            .filter(|dir| check_allowed_dir(dir, &self.config).is_ok())
+            .unwrap_or_else(|| self.paths[0].clone());
+        println!("{}", self.config.messages.current_dir_removed(&self.current_path, &fallback));
+        self.change_dir(fallback);
+        true
+    }
+
+    /// Creates the planned session dir once the pending save is selected, or goes back to the dir
+    /// of the save if it has ended otherwise (e.g. with an invalid name or a declined save).
+    fn settle_session_dir(&mut self, step: MenuStep) -> MenuStep {
+        let Some(parent) = self.session_parent.take() else {
+            return step;
+        };
+        let pending: bool = !matches!(self.state, MenuState::Listing);
+        match step {
+            MenuStep::Selected(_) => {
+                if let Err(e) = create_session_dir(&parent, &self.current_path, &self.config) {
+                    self.current_path = parent;
+                    return MenuStep::Failed(e);
+                }
+                step
+            },
+            MenuStep::Refresh | MenuStep::NeedMoreInput if pending => {
+                self.session_parent = Some(parent);
+                step
+            },
+            _ => {
+                self.current_path = parent;
+                step
+            },
+        }
+    }
+
+    /// Goes back to the dir of the pending save if its session dir is only planned.
+    fn drop_session_plan(&mut self) {
+        if let Some(parent) = self.session_parent.take() {
+            self.current_path = parent;
+        }
+    }
+
+    /// Moves into an existing dir, notifying on_dir_change if it is a different one. The
+    /// configuration of the dir is used from now on.
+    fn change_dir(&mut self, path: String) {
+        if path != self.current_path {
+            self.current_path = path;
+            self.config = get_dir_config(&self.current_path, self.base_config);
+            if let Some(on_dir_change) = &self.config.on_dir_change {
+                on_dir_change.call(Path::new(&self.current_path));
+            }
+        }
+    }
+
+    fn need_new_name(&mut self) -> Result<MenuStep> {
+        self.init_s.clear();
+        println!("{}", self.config.messages.new_name_prompt());
+        self.refresh()
+    }
+
+    fn handle_listing_input(&mut self, line: &str) -> Result<MenuStep> {
+        if self.leave_removed_dir() {
+            // The listed numbers belong to the removed dir.
+            self.init_s = line.to_string();
+            return self.refresh();
+        }
+        if let Some(command) = parse_menu_command(line, self.mode) {
+            return self.run_menu_command(command);
+        }
+        if let Some(file) = self.get_vanished_file(line) {
+            // Removed since the listing, e.g. by another process.
+            println!("{}", self.config.messages.file_vanished(&file));
+            return self.refresh();
+        }
+        let line: String = line.split(' ')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("_");
+        if self.mode == MenuMode::Load {
+            let matches: Vec<(usize, Vec<usize>)> =
+                get_prefix_matches(&line, &self.numbered, &self.config);
+            if matches.len() > 1 {
+                // Not unique, narrow the listing to the matching files.
+                self.state = MenuState::Fuzzy(line, matches);
+                return Ok(MenuStep::Refresh);
+            }
+        }
+        let (path, file): (Option<String>, Option<String>) =
+            parse_menu_file(&line, &self.numbered, &self.current_path, self.mode, &self.config)?;
+        let path: String = path.unwrap_or_else(|| self.current_path.clone());
+        if let Err(e) = check_allowed_dir(&path, &self.config) {
+            println!("{}", self.config.messages.error(&e));
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        if !check_dir_exists(&path) {
+            // Selected path does not exist.
+            println!("{}", self.config.messages.path_not_found(&path));
+            if self.mode == MenuMode::Save && !self.config.read_only {
+                // ask if the new dir needs to be created or not.
+                self.state = MenuState::Confirm(Confirmation::CreateDir { path, file });
+                return Ok(MenuStep::Refresh);
+            }
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        self.change_dir(path);
+        match file {
+            Some(file) => self.select_file(file),
+            None => {
+                self.init_s.clear();
+                self.refresh()
+            },
+        }
+    }
+
+    /// Selects a fuzzy match as if its listing number had been typed. An empty line selects the
+    /// best match, and a new pattern filters the whole listing again.
+    fn handle_fuzzy_input(&mut self, line: &str, pattern: String, matches: Vec<(usize, Vec<usize>)>)
+        -> Result<MenuStep> {
+        let line: &str = line.trim();
+        if let Some(MenuCommand::Fuzzy(pattern)) = parse_menu_command(line, self.mode) {
+            return self.fuzzy_filter(&pattern);
+        }
+        let rank: Option<usize> = if line.is_empty() {
+            Some(0) // Best match.
+        } else {
+            line.parse::<usize>().ok()
+        };
+        match rank.and_then(|rank| matches.get(rank)) {
+            Some((num, _)) => self.handle_listing_input(&num.to_string()),
+            None => {
+                println!("{}", self.config.messages.invalid_input());
+                self.state = MenuState::Fuzzy(pattern, matches);
+                Ok(MenuStep::NeedMoreInput)
+            },
+        }
+    }
+
+    fn handle_search_input(&mut self, line: &str, results: Vec<(String, String)>)
+        -> Result<MenuStep> {
+        let line: &str = line.trim();
+        if line.is_empty() {
+            return self.refresh();
+        }
+        let selected: Option<&(String, String)> = line.parse::<usize>().ok().and_then(|num| results.get(num));
+        let (dir, file): (String, String) = match selected {
+            Some(result) => result.clone(),
+            None => {
+                println!("{}", self.config.messages.invalid_input());
+                self.state = MenuState::Search(results);
+                return Ok(MenuStep::NeedMoreInput);
+            },
+        };
+        if let Err(e) = check_allowed_dir(&dir, &self.config) {
+            println!("{}", self.config.messages.error(&e));
+            self.state = MenuState::Search(results);
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        self.change_dir(dir);
+        self.select_file(file)
+    }
+
+    /// Gets the listed file selected by number if it no longer exists.
+    fn get_vanished_file(&self, line: &str) -> Option<String> {
+        let num: usize = line.trim().parse::<usize>().ok()?;
+        match self.numbered.get(num)? {
+            ListingEntry::File(file) if !Path::new(&self.current_path).join(file).exists() =>
+                Some(file.clone()),
+            _ => None,
+        }
+    }
+
+    fn handle_conflict_input(&mut self, line: &str, file_name: String) -> Result<MenuStep> {
+        if !Path::new(&self.current_path).join(&file_name).exists() {
+            // The conflict is gone, the name can be confirmed again.
+            println!("{}", self.config.messages.file_vanished(&file_name));
+            self.init_s = file_name;
+            return self.refresh();
+        }
+        let action: ConflictAction = match ConflictAction::from_key(line.trim()) {
+            Some(action) => action,
+            None => {
+                println!("{}", self.config.messages.invalid_input());
+                self.state = MenuState::Conflict(file_name);
+                return Ok(MenuStep::NeedMoreInput);
+            },
+        };
+        if action == ConflictAction::MoveExisting && self.config.confirm_move_existing &&
+            self.mode == MenuMode::Save && !self.config.read_only {
+            let new_name: String =
+                get_next_sequential_name(Path::new(&self.current_path), &file_name, &self.config)?;
+            self.state = MenuState::Confirm(Confirmation::MoveExisting { file: file_name, new_name });
+            return Ok(MenuStep::Refresh);
+        }
+        self.apply_conflict_action(action, file_name)
+    }
+
+    /// Applies a conflict action to the existing file. With confirm_final, the actions that keep
+    /// the name are only applied once the final path is confirmed.
+    fn apply_conflict_action(&mut self, action: ConflictAction, file_name: String)
+        -> Result<MenuStep> {
+        let config: &FileManagerConfig = &self.config;
+        if config.confirm_final && self.mode == MenuMode::Save && !config.read_only &&
+            matches!(action, ConflictAction::Replace | ConflictAction::MoveExisting) {
+            let action: Option<ConflictAction> = Some(action);
+            self.state = MenuState::Confirm(Confirmation::Final { file: file_name, action,
+                                                                  claimed: false });
+            return Ok(MenuStep::Refresh);
+        }
+        match apply_conflict_action(action, &self.current_path, file_name, self.mode, config) {
+            Ok(file_name) => {
+                if action == ConflictAction::SequentialNew && self.mode == MenuMode::Save {
+                    self.sequential_number = get_sequential_counter(&file_name, config);
+                }
+                self.select(file_name, false)
+            },
+            Err(Error::NeedNewName) => self.need_new_name(),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Applies the answer of a confirmation.
+    fn confirm(&mut self, confirmation: Confirmation, yes: bool) -> Result<MenuStep> {
+        match (confirmation, yes) {
+            (Confirmation::CreateDir { path, file }, true) => {
+                create_dir(&path)?;
+                self.change_dir(path);
+                match file {
+                    Some(file) => self.select_file(file),
+                    None => {
+                        self.init_s.clear();
+                        self.refresh()
+                    },
+                }
+            },
+            (Confirmation::CreateDir { path, file }, false) => {
+                println!("{}", self.config.messages.dir_not_created());
+                // Keep the typed input so that only the directory has to be fixed.
+                let separator: &str = if path.ends_with('/') { "" } else { "/" };
+                self.init_s = format!("{path}{separator}{}", file.unwrap_or_default());
+                Ok(MenuStep::NeedMoreInput)
+            },
+            (Confirmation::Delete(names), true) => {
+                let mut failed: usize = 0;
+                for name in names.iter() {
+                    let dir: &Path = Path::new(&self.current_path);
+                    if let Err(e) = move_to_trash_with_sidecars(dir, name, &self.config) {
+                        println!("{}", self.config.messages.delete_failed(name, &e));
+                        failed += 1;
+                    }
+                }
+                println!("{}", self.config.messages.deleted_files(names.len() - failed, failed));
+                self.refresh()
+            },
+            (Confirmation::Delete(_), false) => Ok(MenuStep::NeedMoreInput),
+            (Confirmation::RenameSeries(renames), true) => {
+                let messages: &dyn Messages = self.config.messages.as_ref();
+                match rename_all(Path::new(&self.current_path), &renames, messages) {
+                    Ok(()) => println!("{}", self.config.messages.renamed_files(renames.len())),
+                    Err(e) => println!("{}", self.config.messages.error(&e)),
+                }
+                self.refresh()
+            },
+            (Confirmation::RenameSeries(_), false) => Ok(MenuStep::NeedMoreInput),
+            (Confirmation::Renumber(renames), true) => {
+                let messages: &dyn Messages = self.config.messages.as_ref();
+                match rename_all_via_temp(Path::new(&self.current_path), &renames, messages) {
+                    Ok(()) => println!("{}", self.config.messages.renamed_files(renames.len())),
+                    Err(e) => println!("{}", self.config.messages.error(&e)),
+                }
+                self.refresh()
+            },
+            (Confirmation::Renumber(_), false) => Ok(MenuStep::NeedMoreInput),
+            (Confirmation::OverBudget { file, .. }, true) => self.check_file_exists(file),
+            (Confirmation::OverBudget { .. }, false) => self.need_new_name(),
+            (Confirmation::UseExisting { existing, .. }, true) => {
+                self.sequential_number = get_sequential_counter(&existing, &self.config);
+                self.select(existing, false)
+            },
+            (Confirmation::UseExisting { file, .. }, false) => self.check_file_exists(file),
+            (Confirmation::Final { file, action: Some(action), .. }, true) => {
+                let config: &FileManagerConfig = &self.config;
+                let file: String =
+                    apply_conflict_action(action, &self.current_path, file, self.mode, config)?;
+                Ok(MenuStep::Selected(self.full_path(&file)))
+            },
+            (Confirmation::Final { file, .. }, true) =>
+                Ok(MenuStep::Selected(self.full_path(&file))),
+            (Confirmation::Final { file, claimed, .. }, false) => {
+                if claimed {
+                    // The claimed name is left free for the next save.
+                    fs::remove_file(Path::new(&self.current_path).join(file))?;
+                }
+                self.need_new_name()
+            },
+            (Confirmation::MoveExisting { file, .. }, true) =>
+                self.apply_conflict_action(ConflictAction::MoveExisting, file),
+            (Confirmation::MoveExisting { file, .. }, false) => {
+                self.state = MenuState::Conflict(file);
+                Ok(MenuStep::Refresh)
+            },
+        }
+    }
+
+    /// Selects a file of the current dir: applies sequential naming and the extension, and
+    /// checks that it can be saved or loaded.
+    fn select_file(&mut self, file: String) -> Result<MenuStep> {
+        if is_session_save(self.mode, &self.config) && !is_session_dir(&self.current_path) {
+            // The saves into a dir go to its session dir. The first one only plans it, so that
+            // nothing is created until the save is selected (see settle_session_dir).
+            match get_session_dir(&self.current_path) {
+                Some(session_dir) => self.change_dir(session_dir),
+                None => {
+                    let session_dir: String = get_new_session_dir(&self.current_path, &self.config);
+                    self.session_parent =
+                        Some(std::mem::replace(&mut self.current_path, session_dir));
+                },
+            }
+        }
+        let config: &FileManagerConfig = &self.config;
+        if config.verbose_sequential && file.ends_with(config.sequential_separator) {
+            println!("{}", config.messages.getting_sequential_name());
+        }
+        let resolved: Result<(String, Option<u16>)> =
+            resolve_file_name(Path::new(&self.current_path), &file, self.mode, config);
+        let file: String = match resolved {
+            Ok((file, cnt)) => {
+                self.sequential_number = cnt;
+                file
+            },
+            Err(Error::InvalidNameChars) => {
+                println!("{}", config.messages.error(&Error::InvalidNameChars));
+                return Ok(MenuStep::NeedMoreInput);
+            },
+            Err(e @ Error::UnknownFileType(_)) => {
+                println!("{}", config.messages.error(&e));
+                let stem: &str = file.split_once('.').map_or(file.as_str(), |(stem, _)| stem);
+                self.init_s = format!("{stem}.{}", config.extension);
+                return self.refresh();
+            },
+            Err(e) => return Err(e),
+        };
+        self.check_identical_content(file)
+    }
+
+    /// Selects the final file, once its full path is confirmed if confirm_final is set.
+    ///
+    /// \param file: final name of the file, in the current dir.
+    /// \param claimed: the name has been claimed, it is removed if the confirmation is declined.
+    ///
+    fn select(&mut self, file: String, claimed: bool) -> Result<MenuStep> {
+        if self.config.confirm_final {
+            self.state = MenuState::Confirm(Confirmation::Final { file, action: None, claimed });
+            return Ok(MenuStep::Refresh);
+        }
+        Ok(MenuStep::Selected(self.full_path(&file)))
+    }
+
+    /// Checks if a file of the dir has the same content when saving, using it is confirmed next.
+    fn check_identical_content(&mut self, file: String) -> Result<MenuStep> {
+        let config: &FileManagerConfig = &self.config;
+        if let (MenuMode::Save, Some(content)) = (self.mode, &self.content) {
+            if let Some(existing) = find_identical_file(Path::new(&self.current_path), content, config)? {
+                self.state = MenuState::Confirm(Confirmation::UseExisting { existing, file });
+                return Ok(MenuStep::Refresh);
+            }
+        }
+        self.check_size_budget(file)
+    }
+
+    /// Checks if the dir is over dir_size_warn_bytes when saving, the save is confirmed next.
+    fn check_size_budget(&mut self, file: String) -> Result<MenuStep> {
+        if let (MenuMode::Save, Some(budget)) = (self.mode, self.config.dir_size_warn_bytes) {
+            let dir: &Path = Path::new(&self.current_path);
+            let new_size: u64 = self.content.as_ref().map_or(0, |content| content.len() as u64);
+            // The metadata of the listing is reused if it has all the files of the dir.
+            let listing: &MenuFileList = &self.listing;
+            let size: u64 = if listing.dir == dir && listing.omitted == 0 &&
+                listing.empty_note.is_none() {
+                listing.metadata.values().map(fs::Metadata::len).sum()
+            } else {
+                get_dir_size(dir, &self.config)?
+            } + new_size;
+            if size > budget {
+                self.state = MenuState::Confirm(Confirmation::OverBudget { file, size, budget });
+                return Ok(MenuStep::Refresh);
+            }
+        }
+        self.check_file_exists(file)
+    }
+
+    /// Checks if the selected file exists: when saving an existing file goes to the conflict
+    /// actions (or the conflict policy is applied, if set), and when loading it must exist (unless
+    /// allow_load_missing is set).
+    fn check_file_exists(&mut self, file: String) -> Result<MenuStep> {
+        let config: &FileManagerConfig = &self.config;
+        let full_path: PathBuf = Path::new(&self.current_path).join(&file);
+        // A planned session dir is new and empty, there is no other save to claim the name from.
+        let claim: bool =
+            config.claim_sequential_names && !config.read_only && self.session_parent.is_none();
+        if let (MenuMode::Save, Some(_), true) = (self.mode, self.sequential_number, claim) {
+            let base_name: String = is_sequential_name(&file, config);
+            let (file, cnt): (String, u16) =
+                claim_sequential_name(Path::new(&self.current_path), &base_name, config)?;
+            self.sequential_number = Some(cnt);
+            return self.select(file, true);
+        }
+        if full_path.is_file() && self.mode == MenuMode::Save && !config.read_only {
+            // In read only mode an existing file is just selected.
+            let Some(action) = config.conflict_policy else {
+                self.state = MenuState::Conflict(file);
+                return Ok(MenuStep::Refresh);
+            };
+            if action == ConflictAction::Fail {
+                return Err(Error::FileExists(full_path));
+            }
+            return self.apply_conflict_action(action, file);
+        } else if !full_path.is_file() && self.mode == MenuMode::Load && !config.allow_load_missing {
+            println!("{}", config.messages.load_file_missing(&full_path));
+            return self.need_new_name();
+        }
+        self.select(file, false)
+    }
+
+    /// Runs a menu command. Errors are printed so that the menu keeps running, only a manual
+    /// termination is returned.
+    fn run_menu_command(&mut self, command: MenuCommand) -> Result<MenuStep> {
+        let result: Result<MenuStep> = match command {
+            MenuCommand::Peek(arg) => match resolve_file_arg(&arg, &self.current_path, &self.numbered,
+                                                             &self.config) {
+                Some(path) => peek_file(&path, &self.config).map(|_| MenuStep::NeedMoreInput),
+                None => {
+                    println!("{}", self.config.messages.out_of_range(&arg));
+                    Ok(MenuStep::NeedMoreInput)
+                },
+            },
+            MenuCommand::Delete(arg) => self.select_files_to_delete(&arg),
+            MenuCommand::Help => {
+                print_help_banner(&mut io::stdout(), self.mode, self.config.messages.as_ref())?;
+                Ok(MenuStep::NeedMoreInput)
+            },
+            MenuCommand::Find(text) => self.find_in_default_dirs(&text),
+            MenuCommand::Fuzzy(pattern) => self.fuzzy_filter(&pattern),
+            MenuCommand::Diff(first, second) => {
+                let resolve = |arg: &str| resolve_file_arg(arg, &self.current_path, &self.numbered,
+                                                           &self.config);
+                match (resolve(&first), resolve(&second)) {
+                    (Some(first), Some(second)) =>
+                        diff_files(&first, &second, self.config.messages.as_ref())
+                            .map(|_| MenuStep::NeedMoreInput),
+                    _ => {
+                        println!("{}", self.config.messages.pair_out_of_range(&first, &second));
+                        Ok(MenuStep::NeedMoreInput)
+                    },
+                }
+            },
+            MenuCommand::Audit(base) => self.audit_series(&base).map(|_| MenuStep::NeedMoreInput),
+            MenuCommand::RenameSeries(old_base, new_base) =>
+                self.select_series_to_rename(&old_base, &new_base),
+            MenuCommand::Renumber(base) => self.select_series_to_renumber(&base),
+            MenuCommand::Refresh => self.refresh(),
+            MenuCommand::Filter(filter) => {
+                self.series_filter = filter;
+                self.refresh()
+            },
+            #[cfg(feature = "reveal")]
+            MenuCommand::Reveal => {
+                reveal_dir(Path::new(&self.current_path), self.config.messages.as_ref());
+                Ok(MenuStep::NeedMoreInput)
+            },
+        };
+        match result {
+            Err(Error::ManuallyTerminated) => Err(Error::ManuallyTerminated),
+            Err(e) => {
+                println!("{}", self.config.messages.error(&e));
+                Ok(MenuStep::NeedMoreInput)
+            },
+            Ok(step) => Ok(step),
+        }
+    }
+
+    /// Searches the files whose name contains the text (ignoring the case) in all the default
+    /// dirs, the results are numbered to be selected next.
+    fn find_in_default_dirs(&mut self, text: &str) -> Result<MenuStep> {
+        let text: String = text.to_lowercase();
+        let mut results: Vec<(String, String)> = Vec::new();
+        for path in self.paths.iter() {
+            match get_file_list(Path::new(path), &self.config) {
+                Ok(files) => results.extend(files
+                    .into_iter()
+                    .filter(|file| file.to_lowercase().contains(&text))
+                    .map(|file| (path.clone(), file))),
+                Err(e) => println!("{}", self.config.messages.search_failed(path, &e)),
+            }
+        }
+        if results.is_empty() {
+            println!("{}", self.config.messages.no_search_results(&text));
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        self.state = MenuState::Search(results);
+        Ok(MenuStep::Refresh)
+    }
+
+    /// Narrows the listing (default dirs, sub dirs and files) to the entries matching a fuzzy
+    /// pattern, best first.
+    fn fuzzy_filter(&mut self, pattern: &str) -> Result<MenuStep> {
+        let matches: Vec<(usize, Vec<usize>)> = rank_fuzzy_matches(pattern, &self.numbered);
+        if matches.is_empty() {
+            println!("{}", self.config.messages.no_fuzzy_matches(pattern));
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        self.state = MenuState::Fuzzy(pattern.to_string(), matches);
+        Ok(MenuStep::Refresh)
+    }
+
+    /// Selects the files of a series to be renamed to a new base name, the renaming is confirmed
+    /// next. The base names may omit the trailing '_'.
+    fn select_series_to_rename(&mut self, old_base: &str, new_base: &str) -> Result<MenuStep> {
+        if self.config.read_only {
+            return Err(Error::ReadOnly);
+        }
+        check_file_name_chars(new_base)?;
+        let separator: char = self.config.sequential_separator;
+        let old_base: String = format!("{}{}", old_base.trim_end_matches(separator), separator);
+        let new_base: String = format!("{}{}", new_base.trim_end_matches(separator), separator);
+        let files: Vec<String> = get_file_list(Path::new(&self.current_path), &self.config)?;
+        let mut renames: Vec<(String, String)> = get_series(&files, &old_base, &self.config)
+            .into_iter()
+            .map(|(_, name)| (name.clone(), format!("{}{}", new_base, &name[old_base.len()..])))
+            .collect();
+        // The sidecar files follow their files.
+        let sidecar_renames: Vec<(String, String)> = renames
+            .iter()
+            .flat_map(|(old_name, new_name)| get_sidecars(Path::new(&self.current_path), old_name,
+                                                          &self.config)
+                .into_iter()
+                .map(|(sidecar, ext)| (sidecar, sidecar_path(Path::new(new_name), &ext)
+                    .to_string_lossy()
+                    .into_owned())))
+            .collect();
+        renames.extend(sidecar_renames);
+        if renames.is_empty() {
+            println!("{}", self.config.messages.series_not_found(&old_base));
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        for (_, new_name) in renames.iter() {
+            check_file_name_len(new_name, &self.config)?;
+            if Path::new(&self.current_path).join(new_name).exists() {
+                return Err(Error::SeriesTargetExists(new_name.clone()));
+            }
+        }
+        self.state = MenuState::Confirm(Confirmation::RenameSeries(renames));
+        Ok(MenuStep::Refresh)
+    }
+
+    /// Selects the files of a series to be renumbered to contiguous counters from 1, the
+    /// renaming is confirmed next. The base name may omit the trailing '_'.
+    fn select_series_to_renumber(&mut self, base: &str) -> Result<MenuStep> {
+        if self.config.read_only {
+            return Err(Error::ReadOnly);
+        }
+        if self.config.sequential_mode == SequentialMode::Timestamp {
+            println!("{}", self.config.messages.renumber_needs_counters());
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        let separator: char = self.config.sequential_separator;
+        let base: String = format!("{}{}", base.trim_end_matches(separator), separator);
+        let dir: &Path = Path::new(&self.current_path);
+        let files: Vec<String> = get_file_list(dir, &self.config)?;
+        let series: Vec<((u16, &str), &String)> = get_series(&files, &base, &self.config);
+        if series.is_empty() {
+            println!("{}", self.config.messages.series_not_found(&base));
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        let mut renames: Vec<(String, String)> = get_renumber_renames(&series, &base, &self.config);
+        if renames.is_empty() {
+            println!("{}", self.config.messages.audit_result(&base, series.len(), &[]));
+            return Ok(MenuStep::NeedMoreInput);
+        }
+        // The sidecar files follow their files.
+        let sidecar_renames: Vec<(String, String)> = renames
+            .iter()
+            .flat_map(|(old_name, new_name)| get_sidecars(dir, old_name, &self.config)
+                .into_iter()
+                .map(|(sidecar, ext)| (sidecar, sidecar_path(Path::new(new_name), &ext)
+                    .to_string_lossy()
+                    .into_owned())))
+            .collect();
+        renames.extend(sidecar_renames);
+        // The new names may be taken by the renamed files, but not by any other file.
+        for (_, new_name) in renames.iter() {
+            let renamed: bool = renames.iter().any(|(old_name, _)| old_name == new_name);
+            if dir.join(new_name).exists() && !renamed {
+                return Err(Error::SeriesTargetExists(new_name.clone()));
+            }
+        }
+        self.state = MenuState::Confirm(Confirmation::Renumber(renames));
+        Ok(MenuStep::Refresh)
+    }
+
+    /// Prints the counters missing in a series of the current dir.
+    fn audit_series(&self, base: &str) -> Result<()> {
+        let separator: char = self.config.sequential_separator;
+        let base: String = format!("{}{}", base.trim_end_matches(separator), separator);
+        let files: Vec<String> = get_file_list(Path::new(&self.current_path), &self.config)?;
+        let series: Vec<((u16, &str), &String)> = get_series(&files, &base, &self.config);
+        if series.is_empty() {
+            println!("{}", self.config.messages.series_not_found(&base));
+        } else {
+            let missing: Vec<u16> = get_missing_counters(&series);
+            println!("{}", self.config.messages.audit_result(&base, series.len(), &missing));
+        }
+        Ok(())
+    }
+
+    /// Selects the listed files to delete by their numbers, the deletion is confirmed next.
+    fn select_files_to_delete(&mut self, arg: &str) -> Result<MenuStep> {
+        if self.config.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let start: usize = self.numbered
+            .iter()
+            .position(|entry| matches!(entry, ListingEntry::File(_)))
+            .unwrap_or(self.numbered.len());
+        // The recent files are already in the listed files.
+        let end: usize = start + self.listing.files.len();
+        let messages: &dyn Messages = self.config.messages.as_ref();
+        let numbers: Vec<usize> = match parse_number_list(arg, start, end, messages) {
+            Ok(numbers) if !numbers.is_empty() => numbers,
+            Ok(_) => return Ok(MenuStep::NeedMoreInput),
+            Err(msg) => {
+                println!("{}", msg);
+                return Ok(MenuStep::NeedMoreInput);
+            },
+        };
+        let names: Vec<String> = numbers
+            .iter()
+            .filter_map(|num| match &self.numbered[*num] {
+                ListingEntry::File(name) => Some(name.clone()),
+                ListingEntry::Dir(_) => None,
+            })
+            .collect();
+        self.state = MenuState::Confirm(Confirmation::Delete(names));
+        Ok(MenuStep::Refresh)
+    }
+}
+
+/// Turns the result of a menu transition into its step: a manual termination cancels the menu
+/// and any other error makes it fail.
+fn into_menu_step(result: Result<MenuStep>) -> MenuStep {
+    match result {
+        Ok(step) => step,
+        Err(Error::ManuallyTerminated) => MenuStep::Cancelled,
+        Err(e) => MenuStep::Failed(e),
+    }
+}
+
+// ----------------------------------------
+// Mains
+// ----------------------------------------
+
+/// Prints the menu listing of the specified directory once and returns, without reading any input.
+/// The output matches the interactive menu so both stay consistent.
+///
+/// \param path: directory whose map files are listed.
+/// \param default_dirs: default dirs to list, if None the default dir is used.
+///
+pub fn print_map_listing(path: &Path, default_dirs: Option<Vec<String>>) {
+    print_map_listing_with_config(path, default_dirs, &FileManagerConfig::default());
+}
+
+/// Prints the menu listing of the specified directory once, using a custom configuration. An
+/// invalid configuration is printed as an error instead of the listing.
+///
+/// \param path: directory whose map files are listed.
+/// \param default_dirs: default dirs to list, if None the default dir is used.
+/// \param config: file manager configuration.
+///
+pub fn print_map_listing_with_config(path: &Path, default_dirs: Option<Vec<String>>,
+                                     config: &FileManagerConfig) {
+    if let Err(e) = write_map_listing(&mut io::stdout(), path, default_dirs, config) {
+        println!("{}", config.messages.error(&e));
+    }
+}
+
+/// Writes the menu listing of the specified directory into out, e.g. to capture it.
+///
+/// \param out: output of the listing.
+/// \param path: directory whose map files are listed.
+/// \param default_dirs: default dirs to list, if None the default dir is used.
+/// \param config: file manager configuration.
+/// \return: error if the configuration is invalid, the directory cannot be read or out cannot be
+///          written.
+///
+pub fn write_map_listing(out: &mut dyn io::Write, path: &Path, default_dirs: Option<Vec<String>>,
+                         config: &FileManagerConfig) -> Result<()> {
+    config.validate()?;
+    let (_, paths) = init_default_paths(default_dirs, config);
+    let listing: MenuFileList = get_menu_file_list(path, MenuMode::Load, config)?;
+    print_menu_options(out, &path.to_string_lossy(), &paths, &listing, MenuMode::Load, config)?;
+    Ok(())
+}
+
+/// Gets the path of the sidecar file of a map, which shares its name with another extension, e.g.
+/// "forest_007.meta" for "forest_007.map".
+///
+/// \param path: path of the map.
+/// \param sidecar_ext: extension of the sidecar file, without the period.
+/// \return: the path of the sidecar file, whether it exists or not.
+///
+pub fn sidecar_path(path: &Path, sidecar_ext: &str) -> PathBuf {
+    path.with_extension(sidecar_ext)
+}
+
+/// Gets the most recently modified map in a directory.
+///
+/// \param path: directory to search.
+/// \param ext: extension of the maps, without the period.
+/// \return: the path of the latest map, None if there are no maps in the directory.
+///
+pub fn latest_map(path: &Path, ext: &str) -> Result<Option<PathBuf>> {
+    let mut latest: Option<(SystemTime, PathBuf)> = None;
+    for entry in fs::read_dir(path)? {
+        let entry_path: PathBuf = entry?.path();
+        if !entry_path.is_file() || entry_path.extension().unwrap_or_default() != ext {
+            continue;
+        }
+        let modified: SystemTime = fs::metadata(&entry_path)?.modified()?;
+        if latest.as_ref().is_none_or(|(latest_modified, _)| modified > *latest_modified) {
+            latest = Some((modified, entry_path));
+        }
+    }
+    Ok(latest.map(|(_, latest_path)| latest_path))
+}
+
+/// Gets the n most recently modified maps in a directory, e.g. for a "continue where you left
+/// off" menu. Only the n newest files are kept while reading the directory, instead of sorting
+/// all of them.
+///
+/// \param path: directory to search.
+/// \param ext: extension of the maps, without the period.
+/// \param n: maximum number of maps.
+/// \return: the paths of the maps, newest first.
+///
+pub fn recent_maps(path: &Path, ext: &str, n: usize) -> Result<Vec<PathBuf>> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    // Min-heap of the newest maps found, its top is the first to be replaced.
+    let mut recent: BinaryHeap<cmp::Reverse<(SystemTime, PathBuf)>> = BinaryHeap::with_capacity(n);
+    for entry in fs::read_dir(path)? {
+        let entry_path: PathBuf = entry?.path();
+        if !entry_path.is_file() || entry_path.extension().unwrap_or_default() != ext {
+            continue;
+        }
+        let modified: SystemTime = fs::metadata(&entry_path)?.modified()?;
+        if recent.len() < n {
+            recent.push(cmp::Reverse((modified, entry_path)));
+        } else if recent.peek().is_some_and(|cmp::Reverse((oldest, _))| modified > *oldest) {
+            recent.pop();
+            recent.push(cmp::Reverse((modified, entry_path)));
+        }
+    }
+    // The ascending order of Reverse is the newest first.
+    Ok(recent.into_sorted_vec().into_iter().map(|cmp::Reverse((_, map_path))| map_path).collect())
+}
+
+/// Gets the files of a directory with the configured extension, sorted as the menu lists them.
+///
+/// \param path: directory to list.
+/// \param config: file manager configuration, for the extension and sort order.
+/// \return: the files as paths relative to the directory, e.g. "forest_007.map", so that they can
+///          be joined to it.
+///
+pub fn get_file_path_list(path: &Path, config: &FileManagerConfig) -> Result<Vec<PathBuf>> {
+    Ok(get_file_list(path, config)?.into_iter().map(PathBuf::from).collect())
+}
+
+/// Gets the sub directories of a directory, sorted by name.
+///
+/// \param path: directory to list.
+/// \return: the sub directories as paths relative to the directory, e.g. "worlds", so that they
+///          can be joined to it.
+///
+pub fn get_dir_path_list(path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(get_dir_names(path)?.into_iter().map(PathBuf::from).collect())
+}
+
+/// Restores a file from the trash directory into the destination directory, under its original
+/// name, e.g. "forest~002.map" is restored as "forest.map".
+///
+/// \param name: name of the file inside the trash directory, without any dir.
+/// \param dest_dir: directory where the file is restored, it is created if needed.
+/// \return: the path of the restored file. Error::InvalidNameChars if the name is not a plain
+///          file name, Error::TrashEntryNotFound if it is not in the trash, and
+///          Error::RestoreTargetExists if the destination exists, it is never overwritten.
+///
+pub fn restore_from_trash(name: &str, dest_dir: &Path, config: &FileManagerConfig) -> Result<PathBuf> {
+    let mut components = Path::new(name).components();
+    if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+        return Err(Error::InvalidNameChars);
+    }
+    let trash_path: PathBuf = config.trash_dir.join(name);
+    if !trash_path.is_file() {
+        return Err(Error::TrashEntryNotFound(name.to_string()));
+    }
+    let original: String =
+        get_trash_original_name(name, config).unwrap_or_else(|| name.to_string());
+    let dest_path: PathBuf = dest_dir.join(original);
+    create_dir(dest_dir)?;
+    match move_file_no_clobber(&trash_path, &dest_path) {
+        Ok(()) => Ok(dest_path),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(Error::RestoreTargetExists),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(Error::TrashEntryNotFound(name.to_string()))
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Previews the name a typed input would select, applying the same transformations as the menu:
+/// spaces are replaced by '_', a sequential name (ending in '_') gets its counter and the
+/// extension is added if missing. Nothing is written, e.g. for a live "will save as" hint.
+///
+/// \param dir: current dir of the menu, used unless the input has its own path.
+/// \param typed: typed input, e.g. "test_" or "maps/forest".
+/// \param mode: sequential names get the next counter when saving, and the last one when loading.
+/// \param config: file manager configuration.
+/// \return: the resolved file name, Error::InvalidNameChars if the input has no file name.
+///
+pub fn preview_name(dir: &str, typed: &str, mode: MenuMode, config: &FileManagerConfig)
+    -> Result<String> {
+    let typed: String = typed.split(' ')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    let (path, file): (Option<String>, Option<String>) = check_if_path_or_file(&typed);
+    let dir: String = path.map_or_else(|| dir.to_string(), |path| resolve_from_root(&path, config));
+    let file: String = file.ok_or(Error::InvalidNameChars)?;
+    Ok(resolve_file_name(Path::new(&dir), &file, mode, config)?.0)
+}
+
+/// Saves the bytes into a file without user interaction, after validating its path.
+/// The bytes are written to a temporary file of the same dir that is then renamed, so the file
+/// is never left half written.
+///
+/// \param path: path of the file to save, its directory must exist.
+/// \param bytes: content of the file.
+/// \param on_conflict: action applied if the file already exists, None uses conflict_policy
+///        and fails with Error::FileExists if it is not set either.
+/// \param config: file manager configuration.
+/// \return: the path of the saved file, which differs from path if the conflict action renamed
+///          the new file. Error::NeedNewName or Error::FileDeletion if the conflict action
+///          requires another name.
+///
+pub fn save_map(path: &Path, bytes: &[u8], on_conflict: Option<ConflictAction>,
+                config: &FileManagerConfig) -> Result<PathBuf> {
+    let on_conflict: ConflictAction =
+        on_conflict.or(config.conflict_policy).unwrap_or(ConflictAction::Fail);
+    if config.read_only {
+        return Err(Error::ReadOnly);
+    }
+    let (dir, file_name): (&Path, &str) = check_file_path(path, config)?;
+    let dir: String = normalize_dir(&dir.to_string_lossy());
+    check_allowed_save_path(&dir, file_name, config)?;
+    // The replaced file is only overwritten by the rename, so it is kept if the write fails.
+    let replace: bool = path.exists() && on_conflict == ConflictAction::Replace;
+    let file_name: String = if path.exists() && !replace {
+        apply_conflict_action(on_conflict, &dir, file_name.to_string(), MenuMode::Save, config)?
+    } else {
+        file_name.to_string()
+    };
+    let final_path: PathBuf = Path::new(&dir).join(&file_name);
+    let tmp_path: PathBuf = Path::new(&dir).join(format!(".{}.tmp", file_name));
+    let written: io::Result<()> = fs::write(&tmp_path, bytes)
+        .and_then(|_| fs::rename(&tmp_path, &final_path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    if replace {
+        println!("{}", config.messages.replacing(&final_path));
+        // The sidecar files belonged to the replaced file.
+        for (sidecar, _) in get_sidecars(Path::new(&dir), &file_name, config) {
+            fs::remove_file(Path::new(&dir).join(sidecar))?;
+        }
+    }
+    Ok(final_path)
+}
+
+/// Checks that the configuration fits the files of a directory before running the menu, e.g.
+/// that its series use the configured counter width. The series are only checked in the Counter
+/// sequential mode.
+///
+/// \param path: directory to check, relative paths are resolved from root.
+/// \param config: file manager configuration.
+/// \return: the mismatches found, empty if the configuration fits the directory.
+///
+pub fn diagnose(path: &Path, config: &FileManagerConfig) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    if let Err(e) = config.validate() {
+        diagnostics.push(Diagnostic::InvalidConfig(e));
+    }
+    let dir: String = resolve_from_root(&path.to_string_lossy(), config);
+    if !check_dir_exists(&dir) {
+        diagnostics.push(Diagnostic::DirNotFound(PathBuf::from(dir)));
+        return diagnostics;
+    }
+    if check_allowed_dir(&dir, config).is_err() {
+        diagnostics.push(Diagnostic::DirNotAllowed(PathBuf::from(&dir)));
+    }
+    let mut names: Vec<String> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect(),
+        Err(e) => {
+            diagnostics.push(Diagnostic::DirUnreadable(PathBuf::from(dir), e));
+            return diagnostics;
+        },
+    };
+    names.sort_unstable();
+    let extension: String = format!(".{}", config.extension);
+    let stems: Vec<&str> = names.iter().filter_map(|name| name.strip_suffix(&extension)).collect();
+    if stems.is_empty() {
+        let mut extensions: Vec<String> = names
+            .iter()
+            .filter_map(|name| name.rsplit_once('.'))
+            .filter(|(stem, ext)| !stem.is_empty() && !ext.is_empty())
+            .map(|(_, ext)| ext.to_string())
+            .collect();
+        extensions.sort_unstable();
+        extensions.dedup();
+        if !extensions.is_empty() {
+            diagnostics.push(Diagnostic::OtherExtensions(extensions));
+        }
+    }
+    if config.sequential_mode != SequentialMode::Counter {
+        return diagnostics;
+    }
+    let mut checked: Vec<String> = Vec::new();
+    for stem in stems {
+        let Some((base, counter)) = stem.rsplit_once(config.sequential_separator) else {
+            continue;
+        };
+        if base.is_empty() || counter.is_empty() || !counter.bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        let base_name: String = format!("{}{}", base, config.sequential_separator);
+        if checked.contains(&base_name) {
+            continue; // One warning per series.
+        }
+        let value: u64 = counter.parse().unwrap_or(u64::MAX);
+        if config.sequential_padding && counter.len() != config.sequential_padding_len {
+            diagnostics.push(Diagnostic::PaddingMismatch {
+                base_name: base_name.clone(),
+                found: counter.len(),
+                configured: config.sequential_padding_len,
+            });
+        } else if value > config.sequential_max_number as u64 {
+            diagnostics.push(Diagnostic::CounterOverMax {
+                base_name: base_name.clone(),
+                counter: value,
+                max: config.sequential_max_number,
+            });
+        } else {
+            continue;
+        }
+        checked.push(base_name);
+    }
+    diagnostics
+}
+
+/// Creates a test file to test the crate, after validating its path.
+///
+/// \param file_path: path of the file to create, its directory must exist.
+/// \param roots: if Some, the file must be inside one of these directories.
+/// \param config: file manager configuration.
+/// \return: the path of the created file.
+///
+pub fn create_test_file_checked(file_path: &str, roots: Option<&[String]>,
+                                config: &FileManagerConfig) -> Result<PathBuf> {
+    let path: &Path = Path::new(file_path);
+    let (parent, file_name): (&Path, &str) = check_file_path(path, config)?;
+    if let Some(roots) = roots {
+        check_inside_roots(parent, file_name, roots)?;
+    }
+    fs::write(path, "This is just a test file, please delete.")?;
+    Ok(path.to_path_buf())
+}
+
+/// Creates a test file to test the crate.
+#[deprecated(note = "use create_test_file_checked, which validates the file path")]
+pub fn create_test_file(file_path: String) {
+    match fs::write(file_path, "This is just a test file, please delete.") {
+        Ok(_) => println!("File created!"),
+        Err(e) => println!("Failed to crate file {e}"),
+    }
+}
+
+// --------------------------------------------------------------------------------
+// Tests
+// --------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty dir for a test in the temp dir, named after the test.
+    pub(crate) fn test_dir(name: &str) -> PathBuf {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("file_manager_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Creates empty files in a dir.
+    pub(crate) fn touch(dir: &Path, names: &[&str]) {
+        for name in names {
+            fs::write(dir.join(name), "").unwrap();
+        }
+    }
+
+    /// Dir as the menu takes it, ending with '/'.
+    pub(crate) fn dir_arg(dir: &Path) -> String {
+        format!("{}/", dir.display())
+    }
+
+    /// Text of the rendered menu.
+    fn rendered(menu: &FileNameMenu) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        menu.render_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn breadcrumb_drops_the_leading_current_dir() {
+        assert_eq!(format_breadcrumb("./test_dir/"), "test_dir");
+        assert_eq!(format_breadcrumb("./test_dir/maps/"), "test_dir › maps");
+        assert_eq!(format_breadcrumb("./"), ".");
+        assert_eq!(format_breadcrumb("../maps/"), ".. › maps");
+        assert_eq!(format_breadcrumb("/tmp/maps/"), "/ › tmp › maps");
+    }
+
+    #[test]
+    fn typed_dirs_are_normalized() {
+        assert_eq!(check_if_path_or_file("a//b/"), (Some("a/b/".to_string()), None));
+        assert_eq!(check_if_path_or_file("./a/"), (Some("a/".to_string()), None));
+        assert_eq!(check_if_path_or_file("a/./b/"), (Some("a/b/".to_string()), None));
+        assert_eq!(check_if_path_or_file("maps//forest/./test.map"),
+                   (Some("maps/forest/".to_string()), Some("test.map".to_string())));
+        assert_eq!(check_if_path_or_file("//maps/"), (Some("/maps/".to_string()), None));
+        assert_eq!(check_if_path_or_file("./"), (Some("./".to_string()), None));
+    }
+
+    #[test]
+    fn typed_dirs_keep_the_parent_segments() {
+        assert_eq!(normalize_dir("a/../b/"), "a/../b/");
+        assert_eq!(normalize_dir("./../a//"), "../a/");
+    }
+
+    /// Panics if the permissions of a locked dir are not enforced (e.g. when running as root), so
+    /// that the permission tests fail instead of passing without checking anything.
+    #[cfg(unix)]
+    fn assert_permissions_enforced(locked: &Path) {
+        let probe: PathBuf = locked.join(".probe");
+        if fs::write(&probe, b"").is_ok() {
+            let _ = fs::remove_file(&probe);
+            panic!("permissions are not enforced, run the ignored tests as a non-root user");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[ignore = "needs enforced permissions, run with --ignored as a non-root user"]
+    fn unreadable_dir_is_listed_empty_and_can_be_left() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir: PathBuf = test_dir("unreadable_dir");
+        let locked: PathBuf = dir.join("locked");
+        fs::create_dir(&locked).unwrap();
+        touch(&locked, &["hidden.map"]);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        assert_permissions_enforced(&locked);
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&dir)]), &config).unwrap();
+
+        assert!(matches!(menu.handle_input(&dir_arg(&locked)), MenuStep::Refresh));
+        assert_eq!(menu.current_dir(), dir_arg(&locked));
+        assert!(menu.files().is_empty());
+        assert!(rendered(&menu).contains(&config.messages.permission_denied_note()));
+
+        assert!(matches!(menu.handle_input("../"), MenuStep::Refresh));
+        assert_eq!(menu.current_dir(), dir_arg(&dir));
+
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extensionless_files_are_only_listed_when_loading() {
+        let dir: PathBuf = test_dir("extensionless_files");
+        touch(&dir, &["a.map", "b", "c.txt"]);
+        let dirs: Option<Vec<String>> = Some(vec![dir_arg(&dir)]);
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().include_extensionless(true).build().unwrap();
+
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, dirs.clone(), &config).unwrap();
+        assert_eq!(menu.files(), ["a.map", "b"]);
+        match menu.handle_input("b") {
+            MenuStep::Selected(path) => assert_eq!(path, format!("{}b", dir_arg(&dir))),
+            step => panic!("unexpected step {:?}", step),
+        }
+
+        let menu: FileNameMenu = FileNameMenu::new(MenuMode::Save, dirs.clone(), &config).unwrap();
+        assert_eq!(menu.files(), ["a.map"]);
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let menu: FileNameMenu = FileNameMenu::new(MenuMode::Load, dirs, &config).unwrap();
+        assert_eq!(menu.files(), ["a.map"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn typed_sequential_suffix_is_not_doubled() {
+        let dir: PathBuf = test_dir("typed_sequential_suffix");
+        touch(&dir, &["base_007.map"]);
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let preview = |typed: &str| preview_name(&dir_arg(&dir), typed, MenuMode::Save, &config);
+
+        assert_eq!(get_sequential_base_name("base_", &config), "base_");
+        assert_eq!(get_sequential_base_name("base_007_", &config), "base_");
+        assert_eq!(preview("base_").unwrap(), "base_008.map");
+        assert_eq!(preview("base_007_").unwrap(), "base_008.map");
+        assert_eq!(preview("base_007").unwrap(), "base_007.map");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn navigation_cannot_escape_the_allowed_roots() {
+        let dir: PathBuf = test_dir("allowed_roots");
+        let root: PathBuf = dir.join("root");
+        let outside: PathBuf = dir.join("outside");
+        fs::create_dir_all(root.join("inside")).unwrap();
+        fs::create_dir(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().allowed_roots(vec![root.clone()]).build().unwrap();
+        let mut menu: FileNameMenu = FileNameMenu::new(MenuMode::Load,
+                                                       Some(vec![dir_arg(&root.join("inside"))]),
+                                                       &config).unwrap();
+
+        // Free navigation inside the root.
+        menu.handle_input("../");
+        assert_eq!(menu.current_dir(), dir_arg(&root));
+
+        let escapes: [String; 6] = [
+            "../".to_string(),
+            "../outside/".to_string(),
+            "../root/inside/../../".to_string(),
+            dir_arg(&outside),
+            dir_arg(&root.join("link")),
+            dir_arg(&root.join("inside/../../outside")),
+        ];
+        for escape in escapes {
+            menu.handle_input(&escape);
+            assert_eq!(menu.current_dir(), dir_arg(&root), "escaped with {}", escape);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn default_dirs_end_with_one_separator() {
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let dirs: Vec<String> = ["hello", "hello/", "hello//", "/maps/forest"]
+            .map(String::from)
+            .to_vec();
+        let (default, paths) = init_default_paths(Some(dirs), &config);
+        assert_eq!(default, "hello/");
+        assert_eq!(paths, ["hello/", "hello/", "hello/", "/maps/forest/"]);
+
+        let config: FileManagerConfig = FileManagerConfig::builder().root("/base").build().unwrap();
+        let (default, _) = init_default_paths(Some(vec!["maps".to_string()]), &config);
+        assert_eq!(default, "/base/maps/");
+        let (default, _) = init_default_paths(Some(vec!["maps/".to_string()]), &config);
+        assert_eq!(default, "/base/maps/");
+    }
+
+    #[test]
+    fn numbers_select_the_rendered_entry_after_the_dir_changes() {
+        let dir: PathBuf = test_dir("rendered_numbers");
+        touch(&dir, &["a.map", "c.map"]);
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&dir)]), &config).unwrap();
+        let num: usize = menu.entries().iter().position(|entry| entry == "c.map").unwrap();
+        assert!(rendered(&menu).contains(&format!("{}: c.map", num)));
+
+        // Another process adds a file that would be listed before c.map.
+        touch(&dir, &["b.map"]);
+        match menu.handle_input(&num.to_string()) {
+            MenuStep::Selected(path) => assert_eq!(path, format!("{}c.map", dir_arg(&dir))),
+            step => panic!("unexpected step {:?}", step),
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn empty_default_dirs_are_ignored() {
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let (default, paths) =
+            init_default_paths(Some(vec!["".to_string(), "ok/".to_string()]), &config);
+        assert_eq!(default, "ok/");
+        assert_eq!(paths, ["ok/"]);
+
+        for dirs in [None, Some(Vec::new()), Some(vec!["".to_string(), "  ".to_string()])] {
+            let (default, paths) = init_default_paths(dirs, &config);
+            assert_eq!(default, DEFAULT_DIRECTORY);
+            assert_eq!(paths, [DEFAULT_DIRECTORY]);
+        }
+    }
+
+    #[test]
+    fn declined_dir_creation_keeps_the_typed_name() {
+        let dir: PathBuf = test_dir("declined_dir");
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Save, Some(vec![dir_arg(&dir)]), &config).unwrap();
+        let typed: String = format!("{}missing/level.map", dir_arg(&dir));
+        assert!(matches!(menu.handle_input(&typed), MenuStep::Refresh));
+        assert!(matches!(menu.handle_input("n"), MenuStep::NeedMoreInput));
+        assert_eq!(menu.initial_input(), typed);
+        assert!(!dir.join("missing").exists());
+
+        // Retrying the kept input asks again, and the name survives the created dir.
+        let retry: String = menu.initial_input().to_string();
+        assert!(matches!(menu.handle_input(&retry), MenuStep::Refresh));
+        match menu.handle_input("y") {
+            MenuStep::Selected(path) => assert_eq!(path, typed),
+            step => panic!("unexpected step {:?}", step),
+        }
+        assert!(dir.join("missing").is_dir());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names_are_skipped() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir: PathBuf = test_dir("non_utf8");
+        touch(&dir, &["good.map"]);
+        let bad: &OsStr = OsStr::from_bytes(b"bad_\xff.map");
+        fs::write(dir.join(bad), b"").unwrap();
+        fs::create_dir(dir.join(OsStr::from_bytes(b"sub_\xfe"))).unwrap();
+        let config: FileManagerConfig = FileManagerConfig::default();
+
+        let entries: FileEntries =
+            get_file_entries_limited(&dir, usize::MAX, false, &config).unwrap();
+        let names: Vec<&str> = entries.files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["good.map"]);
+        assert_eq!(entries.skipped, [bad.to_os_string()]);
+
+        let menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&dir)]), &config).unwrap();
+        let entries: Vec<String> = menu.entries();
+        assert!(entries.iter().all(|entry| !entry.contains("bad_") && !entry.contains("sub_")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn load_mode_browses_without_writes() {
+        let dir: PathBuf = test_dir("load_without_writes");
+        let maps: PathBuf = dir.join("maps");
+        fs::create_dir(&maps).unwrap();
+        touch(&maps, &["a.map", "b_001.map"]);
+        let missing: PathBuf = maps.join("missing");
+        let listing = || -> Vec<(OsString, SystemTime)> {
+            let mut listing: Vec<(OsString, SystemTime)> = fs::read_dir(&maps).unwrap()
+                .map(|entry| entry.unwrap())
+                .map(|entry| (entry.file_name(), entry.metadata().unwrap().modified().unwrap()))
+                .collect();
+            listing.sort();
+            listing
+        };
+        let before: Vec<(OsString, SystemTime)> = listing();
+
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().create_missing_defaults(true).build().unwrap();
+        let mut menu: FileNameMenu = FileNameMenu::new(MenuMode::Load,
+                                                       Some(vec![dir_arg(&maps),
+                                                                 dir_arg(&missing)]),
+                                                       &config).unwrap();
+        assert_eq!(menu.files(), ["a.map", "b_001.map"]);
+        assert!(matches!(menu.handle_input(&dir_arg(&missing)), MenuStep::NeedMoreInput));
+        match menu.handle_input("b_001.map") {
+            MenuStep::Selected(path) => assert_eq!(path, format!("{}b_001.map", dir_arg(&maps))),
+            step => panic!("unexpected step {:?}", step),
+        }
+        assert!(!missing.exists());
+        assert_eq!(listing(), before);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[ignore = "needs enforced permissions, run with --ignored as a non-root user"]
+    fn read_only_dirs_are_browsed_in_load_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir: PathBuf = test_dir("read_only_load");
+        touch(&dir, &["a.map", "b_001.map"]);
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+        assert_permissions_enforced(&dir);
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().create_missing_defaults(true).build().unwrap();
+        let mut menu: FileNameMenu = FileNameMenu::new(MenuMode::Load,
+                                                       Some(vec![dir_arg(&dir),
+                                                                 dir_arg(&dir.join("missing"))]),
+                                                       &config).unwrap();
+        assert_eq!(menu.files(), ["a.map", "b_001.map"]);
+        match menu.handle_input("b_001.map") {
+            MenuStep::Selected(path) => assert_eq!(path, format!("{}b_001.map", dir_arg(&dir))),
+            step => panic!("unexpected step {:?}", step),
+        }
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn removed_current_dir_falls_back_to_an_existing_dir() {
+        let dir: PathBuf = test_dir("removed_dir");
+        let keep: PathBuf = dir.join("keep");
+        let gone: PathBuf = dir.join("gone");
+        fs::create_dir(&keep).unwrap();
+        fs::create_dir_all(gone.join("sub")).unwrap();
+        touch(&keep, &["a.map"]);
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&keep)]), &config).unwrap();
+        menu.handle_input(&dir_arg(&gone.join("sub")));
+        assert_eq!(menu.current_dir(), dir_arg(&gone.join("sub")));
+
+        // The nearest existing ancestor is used, and the typed input is kept for the new listing.
+        fs::remove_dir_all(&gone).unwrap();
+        assert!(matches!(menu.handle_input("0"), MenuStep::Refresh));
+        assert_eq!(menu.current_dir(), dir_arg(&dir));
+        assert_eq!(menu.initial_input(), "0");
+
+        // The default dir is used when the ancestors are not allowed.
+        fs::create_dir_all(gone.join("sub")).unwrap();
+        let config: FileManagerConfig = FileManagerConfig::builder()
+            .allowed_roots(vec![keep.clone(), gone.join("sub")])
+            .build()
+            .unwrap();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&keep)]), &config).unwrap();
+        menu.handle_input(&dir_arg(&gone.join("sub")));
+        assert_eq!(menu.current_dir(), dir_arg(&gone.join("sub")));
+        fs::remove_dir_all(&gone).unwrap();
+        assert!(matches!(menu.handle_input("0"), MenuStep::Refresh));
+        assert_eq!(menu.current_dir(), dir_arg(&keep));
+        assert_eq!(menu.files(), ["a.map"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_or_empty_extensions_get_the_default_one() {
+        let dir: PathBuf = test_dir("default_extension");
+        let config: FileManagerConfig = FileManagerConfig::default();
+        for typed in ["myname.", "myname", "myname.map"] {
+            assert_eq!(preview_name(&dir_arg(&dir), typed, MenuMode::Save, &config).unwrap(),
+                       "myname.map", "typed {}", typed);
+            let mut menu: FileNameMenu =
+                FileNameMenu::new(MenuMode::Save, Some(vec![dir_arg(&dir)]), &config).unwrap();
+            match menu.handle_input(typed) {
+                MenuStep::Selected(path) => {
+                    assert_eq!(path, format!("{}myname.map", dir_arg(&dir)), "typed {}", typed)
+                },
+                step => panic!("unexpected step {:?} for {}", step, typed),
+            }
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extension_is_added_only_if_required() {
+        let dir: PathBuf = test_dir("require_extension");
+        touch(&dir, &["legacy", "forest.map"]);
+        let preview = |typed: &str, config: &FileManagerConfig| {
+            preview_name(&dir_arg(&dir), typed, MenuMode::Save, config)
+        };
+
+        let config: FileManagerConfig = FileManagerConfig::default();
+        assert!(config.require_extension);
+        assert_eq!(preview("forest", &config).unwrap(), "forest.map");
+        assert_eq!(preview("forest_", &config).unwrap(), "forest_000.map");
+        let menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Save, Some(vec![dir_arg(&dir)]), &config).unwrap();
+        assert_eq!(menu.files(), ["forest.map"]);
+
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().require_extension(false).build().unwrap();
+        assert_eq!(preview("forest", &config).unwrap(), "forest");
+        assert_eq!(preview("forest.map", &config).unwrap(), "forest.map");
+        assert_eq!(preview("forest_", &config).unwrap(), "forest_000.map");
+        assert!(matches!(preview("forest.txt", &config), Err(Error::UnknownFileType(_))));
+        for mode in [MenuMode::Save, MenuMode::Load] {
+            let menu: FileNameMenu =
+                FileNameMenu::new(mode, Some(vec![dir_arg(&dir)]), &config).unwrap();
+            let mut files: Vec<String> = menu.files().to_vec();
+            files.sort();
+            assert_eq!(files, ["forest.map", "legacy"]);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parent_dirs_are_relative_to_the_current_dir() {
+        let config: FileManagerConfig = FileManagerConfig::builder().root("/base").build().unwrap();
+        let current: &str = "/maps/forest/day/";
+        assert_eq!(resolve_typed_dir("../", current, &config), "/maps/forest/");
+        assert_eq!(resolve_typed_dir("..", current, &config), "/maps/forest/");
+        assert_eq!(resolve_typed_dir("../../", current, &config), "/maps/");
+        assert_eq!(resolve_typed_dir("../a/b/", current, &config), "/maps/forest/a/b/");
+        assert_eq!(resolve_typed_dir("../../../../../", current, &config), "/");
+        // Other relative dirs are relative to the root.
+        assert_eq!(resolve_typed_dir("a/../b/", current, &config), "/base/b/");
+
+        let dir: PathBuf = test_dir("parent_dirs");
+        fs::create_dir_all(dir.join("x/y")).unwrap();
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        let config: FileManagerConfig = FileManagerConfig::default();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&dir.join("x/y"))]), &config)
+                .unwrap();
+        menu.handle_input("../../a/b/");
+        assert_eq!(menu.current_dir(), dir_arg(&dir.join("a/b")));
+        menu.handle_input("../");
+        assert_eq!(menu.current_dir(), dir_arg(&dir.join("a")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn outside_root_errors_carry_the_path_and_the_roots() {
+        let dir: PathBuf = test_dir("outside_root_error");
+        let root: PathBuf = dir.join("root");
+        let outside: PathBuf = dir.join("outside");
+        fs::create_dir(&root).unwrap();
+        fs::create_dir(&outside).unwrap();
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().allowed_roots(vec![root.clone()]).build().unwrap();
+
+        let error: Error = save_map(&outside.join("level.map"), b"", None, &config).unwrap_err();
+        let expected: PathBuf = outside.canonicalize().unwrap().join("level.map");
+        match &error {
+            Error::OutsideRoot { path, roots } => {
+                assert_eq!(path, &expected);
+                assert_eq!(roots, &vec![root.clone()]);
+            },
+            error => panic!("unexpected error {:?}", error),
+        }
+        let text: String = error.to_string();
+        assert!(text.contains(&expected.display().to_string()), "{}", text);
+        assert!(text.contains(&root.display().to_string()), "{}", text);
+        assert!(!outside.join("level.map").exists());
+
+        let roots: Vec<String> = vec![root.to_string_lossy().into_owned()];
+        let file: String = outside.join("test.map").to_string_lossy().into_owned();
+        match create_test_file_checked(&file, Some(&roots), &config) {
+            Err(Error::OutsideRoot { path, roots }) => {
+                assert_eq!(path, outside.canonicalize().unwrap().join("test.map"));
+                assert_eq!(roots, vec![root.clone()]);
+            },
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(!outside.join("test.map").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn trashed_files_are_restored_under_their_original_name() {
+        let dir: PathBuf = test_dir("trash_restore");
+        let trash: PathBuf = dir.join("trash");
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().trash_dir(trash.clone()).build().unwrap();
+        let names: [&str; 7] = ["forest.map", "forest.map", "forest~001.map", "a.v2.map",
+                                "a.v2.map", "notes", "notes"];
+        for name in names {
+            touch(&dir, &[name]);
+            move_to_trash(&dir.join(name), name, &config).unwrap();
+        }
+        let mut trashed: Vec<String> = fs::read_dir(&trash).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        trashed.sort();
+        assert_eq!(trashed, ["a.v2.map", "a.v2~001.map", "forest.map", "forest~001.map",
+                             "forest~001~001.map", "notes", "notes~001"]);
+
+        let dest: PathBuf = dir.join("restored");
+        for (name, original) in [("forest~001.map", "forest.map"),
+                                 ("forest~001~001.map", "forest~001.map"),
+                                 ("a.v2~001.map", "a.v2.map"),
+                                 ("notes~001", "notes")] {
+            assert_eq!(restore_from_trash(name, &dest, &config).unwrap(), dest.join(original));
+        }
+        assert!(matches!(restore_from_trash("forest.map", &dest, &config),
+                         Err(Error::RestoreTargetExists)));
+        assert!(trash.join("forest.map").is_file());
+        assert!(matches!(restore_from_trash("forest~001.map", &dest, &config),
+                         Err(Error::TrashEntryNotFound(_))));
+        for name in ["../forest.map", "trash/forest.map", "..", ".", ""] {
+            assert!(matches!(restore_from_trash(name, &dest, &config),
+                             Err(Error::InvalidNameChars)), "restored {}", name);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn name_length_is_limited_in_chars_as_configured() {
+        let dir: PathBuf = test_dir("name_length");
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().max_name_chars(12).build().unwrap();
+        assert_eq!(preview_name(&dir_arg(&dir), "ñandú_ü", MenuMode::Save, &config).unwrap(),
+                   "ñandú_ü.map");
+        // 12 characters, but more bytes.
+        assert!(save_map(&dir.join("ñandú_üé.map"), b"", None, &config).is_ok());
+        match save_map(&dir.join("ñandú_üéx.map"), b"", None, &config) {
+            Err(error @ Error::InvalidNameTooLong(12)) => {
+                assert_eq!(config.messages.error(&error),
+                           "FILE MNG :: Error selected file name is longer than 12.");
+            },
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(matches!(FileManagerConfig::builder().max_name_chars(0).build(),
+                         Err(ConfigError::NoNameChars)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_without_metadata_are_listed_last_by_name() {
+        let dir: PathBuf = test_dir("no_metadata");
+        touch(&dir, &["new.map", "old.map"]);
+        let old: SystemTime = SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::options().write(true).open(dir.join("old.map")).unwrap()
+            .set_modified(old).unwrap();
+        std::os::unix::fs::symlink(dir.join("gone.map"), dir.join("b_broken.map")).unwrap();
+        std::os::unix::fs::symlink(dir.join("gone.map"), dir.join("a_broken.map")).unwrap();
+
+        for (sort_order, expected) in [
+            (SortOrder::MtimeNewest, ["new.map", "old.map", "a_broken.map", "b_broken.map"]),
+            (SortOrder::MtimeOldest, ["old.map", "new.map", "a_broken.map", "b_broken.map"]),
+            (SortOrder::NameAsc, ["a_broken.map", "b_broken.map", "new.map", "old.map"]),
+        ] {
+            let config: FileManagerConfig =
+                FileManagerConfig::builder().sort_order(sort_order).build().unwrap();
+            let entries: FileEntries =
+                get_file_entries_limited(&dir, usize::MAX, false, &config).unwrap();
+            let names: Vec<&str> = entries.files.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(names, expected, "{:?}", sort_order);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn limited_listings_keep_the_first_files_in_sort_order() {
+        let dir: PathBuf = test_dir("limited_sorted");
+        let names: [&str; 5] = ["c.map", "a.map", "e.map", "b.map", "d.map"];
+        touch(&dir, &names);
+        // The later in names, the newer.
+        let now: SystemTime = SystemTime::now();
+        for (age, name) in names.iter().rev().enumerate() {
+            fs::File::options().write(true).open(dir.join(name)).unwrap()
+                .set_modified(now - std::time::Duration::from_secs(60 * age as u64)).unwrap();
+        }
+        let config: FileManagerConfig = FileManagerConfig::builder()
+            .sort_order(SortOrder::MtimeNewest)
+            .max_listed(2)
+            .build()
+            .unwrap();
+        let entries: FileEntries = get_file_entries_limited(&dir, 2, false, &config).unwrap();
+        let listed: Vec<&str> = entries.files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(listed, ["d.map", "b.map"]);
+        assert_eq!(entries.omitted, 3);
+
+        let menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Load, Some(vec![dir_arg(&dir)]), &config).unwrap();
+        assert_eq!(menu.files(), ["d.map", "b.map"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn saves_without_menu_accept_the_extensions_of_the_menu() {
+        let dir: PathBuf = test_dir("save_extensions");
+        let config: FileManagerConfig = FileManagerConfig::builder()
+            .allowed_extra_extensions(vec!["json".to_string()])
+            .build()
+            .unwrap();
+        for name in ["report.json", "forest.v2.map"] {
+            assert_eq!(preview_name(&dir_arg(&dir), name, MenuMode::Save, &config).unwrap(), name);
+            assert_eq!(save_map(&dir.join(name), b"", None, &config).unwrap(), dir.join(name));
+        }
+        for name in ["report.txt", "notes"] {
+            assert!(matches!(save_map(&dir.join(name), b"", None, &config),
+                             Err(Error::UnknownFileType(_))), "saved {}", name);
+        }
+
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().require_extension(false).build().unwrap();
+        assert_eq!(preview_name(&dir_arg(&dir), "notes", MenuMode::Save, &config).unwrap(),
+                   "notes");
+        assert_eq!(save_map(&dir.join("notes"), b"", None, &config).unwrap(), dir.join("notes"));
+        assert!(matches!(save_map(&dir.join("report.json"), b"", None, &config),
+                         Err(Error::UnknownFileType(_))));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn config_errors_and_diagnostics_are_translated() {
+        #[derive(Debug)]
+        struct ShortMessages;
+
+        impl Messages for ShortMessages {
+            fn config_empty_extension(&self) -> String {
+                "no extension".to_string()
+            }
+
+            fn diagnostic_dir_not_found(&self, path: &Path) -> String {
+                format!("missing {}", path.display())
+            }
+        }
+
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().messages(ShortMessages).build().unwrap();
+        let error: Error = Error::Config(ConfigError::EmptyExtension);
+        assert_eq!(config.messages.error(&error), "no extension");
+        assert_eq!(error.to_string(), "FILE MNG :: Config error extension is empty.");
+
+        let diagnostic: Diagnostic = Diagnostic::DirNotFound(PathBuf::from("maps"));
+        assert_eq!(config.messages.diagnostic(&diagnostic), "missing maps");
+        assert_eq!(diagnostic.to_string(), "FILE MNG :: Warning directory maps does not exist.");
+        assert_eq!(config.messages.diagnostic(&Diagnostic::InvalidConfig(
+                       ConfigError::EmptyExtension)), "no extension");
+    }
+
+    #[test]
+    fn prefix_matches_skip_the_template_prefix() {
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().name_template("map_{name}").build().unwrap();
+        let entries: Vec<ListingEntry> = ["map_mapping.map", "map_maple.map", "other_map.map"]
+            .into_iter()
+            .map(|file| ListingEntry::File(file.to_string()))
+            .collect();
+        assert_eq!(get_prefix_matches("map", &entries, &config),
+                   [(0, vec![4, 5, 6]), (1, vec![4, 5, 6])]);
+
+        let config: FileManagerConfig = FileManagerConfig::default();
+        assert_eq!(get_prefix_matches("map", &entries, &config),
+                   [(0, vec![0, 1, 2]), (1, vec![0, 1, 2])]);
+    }
+
+    #[test]
+    fn session_dirs_are_created_in_the_current_dir_once_the_save_is_confirmed() {
+        let dir: PathBuf = test_dir("session_dirs_are_created_once_confirmed");
+        fs::create_dir(dir.join("sub")).unwrap();
+        let now: SystemTime = UNIX_EPOCH + std::time::Duration::from_secs(1_706_745_599);
+        let config: FileManagerConfig = FileManagerConfig::builder()
+            .auto_session_dir(true)
+            .confirm_final(true)
+            .clock(FixedClock(now))
+            .build()
+            .unwrap();
+        let mut menu: FileNameMenu =
+            FileNameMenu::new(MenuMode::Save, Some(vec![dir_arg(&dir)]), &config).unwrap();
+        let sub: usize = menu.numbered
+            .iter()
+            .position(|entry| matches!(entry, ListingEntry::Dir(d) if d.ends_with("sub/")))
+            .unwrap();
+        assert!(matches!(menu.handle_input(&sub.to_string()), MenuStep::Refresh));
+        let session_dir: PathBuf = dir.join("sub").join("run_20240131_235959");
+
+        // Invalid names and declined saves leave no session dir behind.
+        assert!(matches!(menu.handle_input(".hidden"), MenuStep::NeedMoreInput));
+        assert!(matches!(menu.handle_input("notes.txt"), MenuStep::Refresh));
+        assert!(matches!(menu.handle_input("notes.map"), MenuStep::Refresh));
+        assert!(rendered(&menu).contains("run_20240131_235959/notes.map"));
+        assert!(!session_dir.exists());
+        assert!(matches!(menu.handle_input("n"), MenuStep::Refresh));
+        assert!(!session_dir.exists());
+        assert_eq!(menu.current_dir(), &format!("{}sub/", dir_arg(&dir)));
+
+        assert!(matches!(menu.handle_input("notes.map"), MenuStep::Refresh));
+        match menu.handle_input("y") {
+            MenuStep::Selected(path) => assert_eq!(Path::new(&path), session_dir.join("notes.map")),
+            _ => panic!("the save is not selected"),
+        }
+        assert!(session_dir.is_dir());
+        assert!(!dir.join("run_20240131_235959").exists());
+        let _ = fs::remove_dir_all(&dir);
     }
 }
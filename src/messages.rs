@@ -0,0 +1,611 @@
+//! Menu messages.
+//! Every text printed by the menu goes through the Messages trait, so that it can be translated.
+//! Each method returns the English text by default, so a translation only needs to implement the
+//! methods it changes.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{ConfigError, ConflictAction, Diagnostic, Error, MenuMode, DIR_CONFIG_FILE};
+
+// --------------------------------------------------------------------------------
+// Definitions
+// --------------------------------------------------------------------------------
+
+/// Texts printed by the menu, set in the configuration. The menu commands and the input keys
+/// (e.g. 'y', 'n' or the conflict actions) are not translated.
+pub trait Messages: fmt::Debug + Send + Sync {
+    // ----------------------------------------
+    // Errors
+    // ----------------------------------------
+
+    /// Message of an error printed by the menu, formatted with the methods below. The `Display`
+    /// of `Error` uses the English messages, use this method to print the errors returned by the
+    /// menu in the same language. The external errors (e.g. io) keep their own message.
+    fn error(&self, error: &Error) -> String {
+        match *error {
+            Error::Io(ref err) => err.to_string(),
+            #[cfg(feature = "interactive")]
+            Error::Cmd(ref err) => err.to_string(),
+            Error::Config(ref err) => self.config_error(err),
+            Error::ManuallyTerminated => self.error_manually_terminated(),
+            Error::NeedNewName => self.error_need_new_name(),
+            Error::FileDeletion => self.error_file_deletion(),
+            Error::InvalidNameTooLong(max) => self.error_name_too_long(max),
+            Error::InvalidNameChars => self.error_name_chars(),
+            Error::OutsideRoot { ref path, ref roots } => self.error_outside_root(path, roots),
+            Error::InvalidSequentialName(max) => self.error_sequential_name(max),
+            Error::UnknownFileType(ref ext) => self.error_unknown_file_type(ext),
+            Error::RestoreTargetExists => self.error_restore_target_exists(),
+            Error::TrashEntryNotFound(ref name) => self.error_trash_entry_not_found(name),
+            Error::SeriesTargetExists(ref name) => self.error_series_target_exists(name),
+            Error::ReadOnly => self.error_read_only(),
+            Error::DirCreateFailed { ref path, ref source } =>
+                self.error_dir_create_failed(path, source),
+            Error::FileExists(ref path) => self.error_file_exists(path),
+        }
+    }
+
+    fn error_manually_terminated(&self) -> String {
+        "FILE MNG :: File selection has been manually terminated with CTRL+D".to_string()
+    }
+
+    fn error_need_new_name(&self) -> String {
+        "FILE MNG :: File selection needs to be re-run.".to_string()
+    }
+
+    fn error_file_deletion(&self) -> String {
+        "FILE MNG :: Specified file has been deleted.".to_string()
+    }
+
+    fn error_name_too_long(&self, max: usize) -> String {
+        format!("FILE MNG :: Error selected file name is longer than {}.", max)
+    }
+
+    fn error_name_chars(&self) -> String {
+        "FILE MNG :: Error selected file name is empty or has invalid characters.".to_string()
+    }
+
+    fn error_outside_root(&self, path: &Path, roots: &[PathBuf]) -> String {
+        let roots: Vec<String> = roots.iter().map(|root| root.display().to_string()).collect();
+        format!("FILE MNG :: Error {} is outside the allowed roots, use one of: {}.",
+                path.display(), roots.join(", "))
+    }
+
+    fn error_sequential_name(&self, max: u16) -> String {
+        format!("FILE MNG :: Error sequential name count larger than {}.", max)
+    }
+
+    fn error_unknown_file_type(&self, ext: &str) -> String {
+        format!("FILE MNG :: Error unsupported file type, use {}.", ext)
+    }
+
+    fn error_restore_target_exists(&self) -> String {
+        "FILE MNG :: Error restored file already exists in the destination.".to_string()
+    }
+
+    fn error_trash_entry_not_found(&self, name: &str) -> String {
+        format!("FILE MNG :: Error {} is not in the trash.", name)
+    }
+
+    fn error_series_target_exists(&self, name: &str) -> String {
+        format!("FILE MNG :: Error {} already exists, the series has not been renamed.", name)
+    }
+
+    fn error_read_only(&self) -> String {
+        "FILE MNG :: Error the action would modify the disk in read only mode.".to_string()
+    }
+
+    fn error_dir_create_failed(&self, path: &Path, source: &io::Error) -> String {
+        format!("FILE MNG :: Error directory {} could not be created: {}.", path.display(), source)
+    }
+
+    fn error_file_exists(&self, path: &Path) -> String {
+        format!("FILE MNG :: Error {} already exists.", path.display())
+    }
+
+    // ----------------------------------------
+    // Configuration
+    // ----------------------------------------
+
+    /// Message of an invalid configuration, formatted with the methods below. The `Display` of
+    /// `ConfigError` uses the English messages.
+    fn config_error(&self, error: &ConfigError) -> String {
+        match *error {
+            ConfigError::PaddingTooShort => self.config_padding_too_short(),
+            ConfigError::NoPrintColumns => self.config_no_print_columns(),
+            ConfigError::NoNameChars => self.config_no_name_chars(),
+            ConfigError::EmptyExtension => self.config_empty_extension(),
+            ConfigError::ExtensionWithPeriod => self.config_extension_with_period(),
+            ConfigError::InvalidNameTemplate => self.config_invalid_name_template(),
+            ConfigError::InvalidSequentialSeparator => self.config_invalid_sequential_separator(),
+            ConfigError::InvalidDirSetting(line) => self.config_invalid_dir_setting(line),
+        }
+    }
+
+    fn config_padding_too_short(&self) -> String {
+        "FILE MNG :: Config error sequential padding is too short for the max number.".to_string()
+    }
+
+    fn config_no_print_columns(&self) -> String {
+        "FILE MNG :: Config error print columns must be at least 1.".to_string()
+    }
+
+    fn config_no_name_chars(&self) -> String {
+        "FILE MNG :: Config error max name chars must be at least 1.".to_string()
+    }
+
+    fn config_empty_extension(&self) -> String {
+        "FILE MNG :: Config error extension is empty.".to_string()
+    }
+
+    fn config_extension_with_period(&self) -> String {
+        "FILE MNG :: Config error extension must not contain a period.".to_string()
+    }
+
+    fn config_invalid_name_template(&self) -> String {
+        "FILE MNG :: Config error name template must contain {name}, and {counter} can only be at \
+         its end.".to_string()
+    }
+
+    fn config_invalid_sequential_separator(&self) -> String {
+        "FILE MNG :: Config error sequential separator must not be a digit, a '.' or a '/'."
+            .to_string()
+    }
+
+    fn config_invalid_dir_setting(&self, line: usize) -> String {
+        format!("FILE MNG :: Config error invalid setting in line {} of {}.", line, DIR_CONFIG_FILE)
+    }
+
+    // ----------------------------------------
+    // Diagnostics
+    // ----------------------------------------
+
+    /// Warning of a diagnostic found by `diagnose`, formatted with the methods below. The
+    /// `Display` of `Diagnostic` uses the English messages.
+    fn diagnostic(&self, diagnostic: &Diagnostic) -> String {
+        match *diagnostic {
+            Diagnostic::InvalidConfig(ref err) => self.config_error(err),
+            Diagnostic::DirNotFound(ref path) => self.diagnostic_dir_not_found(path),
+            Diagnostic::DirNotAllowed(ref path) => self.diagnostic_dir_not_allowed(path),
+            Diagnostic::DirUnreadable(ref path, ref err) =>
+                self.diagnostic_dir_unreadable(path, err),
+            Diagnostic::PaddingMismatch { ref base_name, found, configured } =>
+                self.diagnostic_padding_mismatch(base_name, found, configured),
+            Diagnostic::CounterOverMax { ref base_name, counter, max } =>
+                self.diagnostic_counter_over_max(base_name, counter, max),
+            Diagnostic::OtherExtensions(ref extensions) =>
+                self.diagnostic_other_extensions(extensions),
+        }
+    }
+
+    fn diagnostic_dir_not_found(&self, path: &Path) -> String {
+        format!("FILE MNG :: Warning directory {} does not exist.", path.display())
+    }
+
+    fn diagnostic_dir_not_allowed(&self, path: &Path) -> String {
+        format!("FILE MNG :: Warning directory {} is outside the allowed roots.", path.display())
+    }
+
+    fn diagnostic_dir_unreadable(&self, path: &Path, error: &io::Error) -> String {
+        format!("FILE MNG :: Warning directory {} cannot be read: {}", path.display(), error)
+    }
+
+    fn diagnostic_padding_mismatch(&self, base_name: &str, found: usize, configured: usize)
+        -> String {
+        format!("FILE MNG :: Warning existing series {} uses {}-digit counters but the config uses \
+                 {}.", base_name, found, configured)
+    }
+
+    fn diagnostic_counter_over_max(&self, base_name: &str, counter: u64, max: u16) -> String {
+        format!("FILE MNG :: Warning existing series {} has counter {}, over the max {}.",
+                base_name, counter, max)
+    }
+
+    fn diagnostic_other_extensions(&self, extensions: &[String]) -> String {
+        format!("FILE MNG :: Warning no files with the configured extension, but there are files \
+                 with: {}.", extensions.join(", "))
+    }
+
+    // ----------------------------------------
+    // Listing
+    // ----------------------------------------
+
+    /// Instructions printed with the listing, one per line.
+    fn help_banner(&self, mode: MenuMode) -> String {
+        let mut lines: Vec<&str> = vec![
+            "Input the name of the file to be saved:",
+            " - Input a number to preselect a directory or a file.",
+            " - Input a name starting with = (e.g. =2024) to use it as typed, even if it is a \
+             number.",
+            " - Input a name ending with / to specify a new absolute or relative (from the root \
+             dir, or from the current dir if it starts with ../) path.",
+            " - Press CTRL+C to restart the input.",
+            " - Press CTRL+D to exit (may need to press CTRL+C first).",
+            " - A name ending in _ (e.g. test_), will be transformed into a sequential name.",
+        ];
+        if mode == MenuMode::Load {
+            lines.extend([
+                " - Input the start of a name (e.g. for) to select the only file starting with \
+                 it, or to narrow the listing to them.",
+                " - Input 'peek <number or name>' to print the beginning of a file.",
+                " - Input 'del <numbers>' (e.g. del 3,5,7-9) to delete files.",
+                " - Input 'audit <base>' (e.g. audit test_) to list the missing counters of a \
+                 series.",
+            ]);
+        }
+        lines.extend([
+            " - Input '/<text>' (e.g. /frst) to narrow the listing to the entries containing the \
+             characters of the text in order.",
+            " - Input 'find <text>' to search the files containing the text in all the default \
+             directories.",
+            " - Input 'diff <number or name> <number or name>' to compare the size and \
+             modification time of two files.",
+            " - Input 'renameseries <old base> <new base>' to rename all the files of a series.",
+            " - Input 'renumber <base>' to rename the files of a series to contiguous counters \
+             from 1.",
+            " - Input 'filter <all|series|single|latest>' to list all files, only sequential \
+             series, only single files or the latest file of each series.",
+        ]);
+        if cfg!(feature = "reveal") {
+            lines.push(" - Input 'reveal' to open the current directory in the file explorer.");
+        }
+        lines.extend([
+            " - Input 'refresh' (or 'r!') to read the current directory again.",
+            " - Input 'help' to print these instructions again.",
+        ]);
+        lines.join("\n")
+    }
+
+    /// Printed instead of the help banner when show_help_banner is not set.
+    fn short_help(&self) -> String {
+        "Input the name of the file ('help' for instructions):".to_string()
+    }
+
+    fn default_dirs_header(&self, count: usize) -> String {
+        format!("Default directories (relative): ({})", count)
+    }
+
+    fn current_dir_header(&self, breadcrumb: &str) -> String {
+        format!("Current dir: {}", breadcrumb)
+    }
+
+    fn sub_dirs_header(&self, count: usize) -> String {
+        format!("Sub dirs: ({})", count)
+    }
+
+    /// Header of the files, filter_note is empty or one of the filter notes.
+    fn files_header(&self, count: usize, filter_note: &str) -> String {
+        format!("Files: ({}){}", count, filter_note)
+    }
+
+    /// Header of the most recently modified files, listed after the files when loading.
+    fn recent_header(&self, count: usize) -> String {
+        format!("Recent: ({})", count)
+    }
+
+    /// Header of the merged listing (dirs and files), filter_note as in files_header.
+    fn entries_header(&self, count: usize, filter_note: &str) -> String {
+        format!("Entries: ({}){}", count, filter_note)
+    }
+
+    fn filter_note_series(&self) -> String {
+        " [filter: only series]".to_string()
+    }
+
+    fn filter_note_single(&self) -> String {
+        " [filter: no series]".to_string()
+    }
+
+    fn filter_note_latest(&self) -> String {
+        " [filter: latest of each series]".to_string()
+    }
+
+    /// Printed in place of the files of a dir that cannot be read.
+    fn permission_denied_note(&self) -> String {
+        "(Permission denied)".to_string()
+    }
+
+    /// Printed in place of the files when the filter hides all of them.
+    fn filtered_note(&self) -> String {
+        "(No files match the filter)".to_string()
+    }
+
+    /// Printed after the listed files when max_listed is reached.
+    fn omitted_files(&self, count: usize) -> String {
+        format!("    ... and {} more (type the name to select them).", count)
+    }
+
+    fn skipping_non_utf8(&self, name: &str) -> String {
+        format!("FILE MNG :: Skipping {}, the name is not valid UTF-8.", name)
+    }
+
+    fn creating_default_dir(&self, path: &str) -> String {
+        format!("Creating default dir {}", path)
+    }
+
+    fn creating_session_dir(&self, path: &str) -> String {
+        format!("Creating session dir {}", path)
+    }
+
+    // ----------------------------------------
+    // Input
+    // ----------------------------------------
+
+    fn invalid_input(&self) -> String {
+        "Invalid input, try again.".to_string()
+    }
+
+    fn empty_input(&self) -> String {
+        "Empty input, try again.".to_string()
+    }
+
+    /// A number or a name that does not match any listed entry.
+    fn out_of_range(&self, input: &str) -> String {
+        format!("{} is out of range, try again.", input)
+    }
+
+    /// Printed after out_of_range when a number is out of the listing.
+    fn literal_name_hint(&self, input: &str) -> String {
+        format!("To use {} as a file name, input ={}.", input, input)
+    }
+
+    fn pair_out_of_range(&self, first: &str, second: &str) -> String {
+        format!("{} or {} is out of range, try again.", first, second)
+    }
+
+    /// An entry of a number list (e.g. "3,5,7-9") that is not a number or a range.
+    fn invalid_number_entry(&self, entry: &str) -> String {
+        format!("{} is not a valid number or range.", entry)
+    }
+
+    /// An entry of a number list out of the [first, last] files.
+    fn number_entry_out_of_range(&self, entry: &str, first: usize, last: usize) -> String {
+        format!("{} is out of range, only files {}-{} can be selected.", entry, first, last)
+    }
+
+    fn new_name_prompt(&self) -> String {
+        "Please input a new name.".to_string()
+    }
+
+    fn path_not_found(&self, path: &str) -> String {
+        format!("Selected path does not exists: {}", path)
+    }
+
+    fn getting_sequential_name(&self) -> String {
+        "Getting sequential name...".to_string()
+    }
+
+    fn load_file_missing(&self, path: &Path) -> String {
+        format!("FILE MNG :: file {} does not exists while loading.", path.display())
+    }
+
+    /// Printed when a listed file has been removed before it is selected, e.g. by another process.
+    fn file_vanished(&self, name: &str) -> String {
+        format!("FILE MNG :: {} no longer exists, listing refreshed.", name)
+    }
+
+    /// Printed after the error of an invalid dir config file, see dir_overrides.
+    fn dir_config_ignored(&self, path: &str) -> String {
+        format!("FILE MNG :: The settings of {} are ignored.", path)
+    }
+
+    /// Printed when the current dir has been removed, e.g. by another process.
+    fn current_dir_removed(&self, path: &str, fallback: &str) -> String {
+        format!("FILE MNG :: Current dir {} no longer exists, moving to {}.", path, fallback)
+    }
+
+    /// Printed when the menu is terminated because of input_timeout.
+    fn input_timeout(&self, timeout: Duration) -> String {
+        format!("FILE MNG :: No input for {:?}, the menu has been terminated.", timeout)
+    }
+
+    // ----------------------------------------
+    // Conflicts
+    // ----------------------------------------
+
+    fn conflict_header(&self, path: &Path) -> String {
+        format!("FILE MNG :: file {} already exits while saving.", path.display())
+    }
+
+    fn conflict_input_header(&self) -> String {
+        "Input:".to_string()
+    }
+
+    /// Option of the conflict menu, trash_dir is where the Delete action moves the file.
+    fn conflict_option(&self, action: ConflictAction, trash_dir: &Path) -> String {
+        match action {
+            ConflictAction::Delete => format!("  '{}' to {} (moved to {}).", action.key(),
+                                              self.conflict_action(action), trash_dir.display()),
+            _ => format!("  '{}' to {}.", action.key(), self.conflict_action(action)),
+        }
+    }
+
+    /// Short description of a conflict action.
+    fn conflict_action(&self, action: ConflictAction) -> String {
+        match action {
+            ConflictAction::Replace => "replace existing file",
+            ConflictAction::MoveExisting => "turn existing file into sequential naming",
+            ConflictAction::SequentialNew => "turn new file into sequential naming",
+            ConflictAction::NewName => "select a new name",
+            ConflictAction::Delete => "delete the specified file",
+            ConflictAction::Fail => "keep the existing file and fail",
+        }.to_string()
+    }
+
+    fn replacing(&self, path: &Path) -> String {
+        format!("Replacing {}...", path.display())
+    }
+
+    fn renaming(&self, path: &Path, new_path: &str) -> String {
+        format!("Renaming {} to {}", path.display(), new_path)
+    }
+
+    fn moved_to_trash(&self, path: &Path, trash_path: &Path) -> String {
+        format!("File {} has been moved to {}.", path.display(), trash_path.display())
+    }
+
+    // ----------------------------------------
+    // Confirmations
+    // ----------------------------------------
+
+    fn yes_no_prompt(&self) -> String {
+        "Input <y>/<yes> or <n>/<no>:".to_string()
+    }
+
+    /// Followed by the list of dirs when there are more than one.
+    fn confirm_create_dirs(&self, count: usize) -> String {
+        if count > 1 {
+            format!("Create {} new dirs?", count)
+        } else {
+            "Create new dir?".to_string()
+        }
+    }
+
+    fn dir_not_created(&self) -> String {
+        "New directory not created, input a new one.".to_string()
+    }
+
+    /// Followed by the list of files.
+    fn confirm_delete(&self, count: usize, trash_dir: &Path) -> String {
+        format!("Delete {} files (moved to {})?", count, trash_dir.display())
+    }
+
+    fn delete_failed(&self, name: &str, error: &Error) -> String {
+        format!("Failed to delete {}: {}", name, self.error(error))
+    }
+
+    fn deleted_files(&self, deleted: usize, failed: usize) -> String {
+        format!("Deleted {} files, {} failed.", deleted, failed)
+    }
+
+    fn over_budget(&self, dir: &str, size: u64, budget: u64) -> String {
+        format!("FILE MNG :: Warning {} has {} bytes of files, over the {} bytes budget.", dir,
+                size, budget)
+    }
+
+    fn confirm_save_anyway(&self, file: &str) -> String {
+        format!("Save {} anyway?", file)
+    }
+
+    /// Followed by the list of renames.
+    fn confirm_rename_series(&self, count: usize) -> String {
+        format!("Rename {} files?", count)
+    }
+
+    fn renamed_files(&self, count: usize) -> String {
+        format!("Renamed {} files.", count)
+    }
+
+    fn restore_failed(&self, name: &str, error: &Error) -> String {
+        format!("Failed to restore {}: {}", name, self.error(error))
+    }
+
+    fn confirm_use_existing(&self, existing: &str, file: &str) -> String {
+        format!("File {} has the same content, use it instead of saving {}?", existing, file)
+    }
+
+    /// Resolved path of the selected file, followed by the yes/no prompt.
+    fn confirm_final(&self, path: &str) -> String {
+        format!("Will use: {}", path)
+    }
+
+    fn confirm_move_existing(&self, path: &str, new_path: &str, file: &str) -> String {
+        format!("Rename the existing file {} -> {}, and save the new file as {}?", path, new_path,
+                file)
+    }
+
+    // ----------------------------------------
+    // Commands
+    // ----------------------------------------
+
+    fn peek_header(&self, path: &Path, len: usize) -> String {
+        format!("---- {} (first {} bytes) ----", path.display(), len)
+    }
+
+    fn diff_header(&self) -> String {
+        "---- diff ----".to_string()
+    }
+
+    /// Size and modification time (UTC, None if unknown) of a compared file.
+    fn diff_file(&self, path: &Path, size: u64, modified: Option<&str>) -> String {
+        format!("    {}: {} bytes, modified {} UTC", path.display(), size,
+                modified.unwrap_or("unknown"))
+    }
+
+    fn diff_newer(&self, name: &str) -> String {
+        format!("{} is newer.", name)
+    }
+
+    fn diff_same_time(&self) -> String {
+        "Both have the same modification time.".to_string()
+    }
+
+    fn diff_larger(&self, name: &str) -> String {
+        format!("{} is larger.", name)
+    }
+
+    fn diff_same_size(&self) -> String {
+        "Both have the same size.".to_string()
+    }
+
+    fn search_failed(&self, path: &str, error: &Error) -> String {
+        format!("Cannot search {}: {}", path, self.error(error))
+    }
+
+    fn no_search_results(&self, text: &str) -> String {
+        format!("No files containing {} in the default directories.", text)
+    }
+
+    fn search_header(&self, count: usize) -> String {
+        format!("Files found in the default directories: ({})", count)
+    }
+
+    fn search_footer(&self) -> String {
+        "Input a number to select a file, or an empty line to go back.".to_string()
+    }
+
+    fn no_fuzzy_matches(&self, pattern: &str) -> String {
+        format!("No entries matching {}.", pattern)
+    }
+
+    fn fuzzy_header(&self, pattern: &str, count: usize) -> String {
+        format!("Entries matching {}: ({})", pattern, count)
+    }
+
+    fn fuzzy_footer(&self) -> String {
+        "Press Enter to select the best match, input a number to select another one, '/<text>' \
+         to filter again or CTRL+C to go back.".to_string()
+    }
+
+    fn series_not_found(&self, base: &str) -> String {
+        format!("No files found in the series {}.", base)
+    }
+
+    /// Result of the audit command, missing is empty when there are no gaps.
+    fn audit_result(&self, base: &str, count: usize, missing: &[u16]) -> String {
+        if missing.is_empty() {
+            format!("Series {}: {} files, no missing counters.", base, count)
+        } else {
+            let missing: Vec<String> = missing.iter().map(|cnt| cnt.to_string()).collect();
+            format!("Series {}: {} files, missing counters {}.", base, count, missing.join(", "))
+        }
+    }
+
+    fn renumber_needs_counters(&self) -> String {
+        "Series cannot be renumbered in the timestamp sequential mode.".to_string()
+    }
+
+    /// Printed when the reveal command cannot open the dir.
+    fn reveal_failed(&self, opener: &str, dir: &Path, reason: &str) -> String {
+        format!("FILE MNG :: Warning {} could not open {}: {}", opener, dir.display(), reason)
+    }
+}
+
+/// Messages in English, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnglishMessages;
+
+impl Messages for EnglishMessages {}
@@ -0,0 +1,611 @@
+//! Sequential naming.
+//! Sequential names add a suffix to a base name ending in '_', e.g. "test_" -> "test_004.map",
+//! so that new versions of a file can be saved without overwriting the previous ones. The
+//! separator and the zero padding are configurable, e.g. "test-" -> "test-4.map". These
+//! functions are used by the menu, and can be used directly to name files without it.
+
+use std::fs::OpenOptions;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::{get_file_list, Error, FileManagerConfig, Result, SequentialMode};
+
+// --------------------------------------------------------------------------------
+// Definitions
+// --------------------------------------------------------------------------------
+
+/// Default character that ends the base name of a sequential name, see sequential_separator.
+pub const SEQUENTIAL_NAMING_CHAR: char = '_';
+const TIMESTAMP_LEN: usize = 15; // YYYYMMDD_HHMMSS
+
+// --------------------------------------------------------------------------------
+// Implementations
+// --------------------------------------------------------------------------------
+
+
+/// Formats the current UTC time, from the configured clock, as a YYYYMMDD_HHMMSS timestamp.
+pub(crate) fn current_timestamp(config: &FileManagerConfig) -> String {
+    let secs: u64 = config.clock.now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    format_timestamp(secs)
+}
+
+/// Formats the seconds since the unix epoch as a UTC YYYYMMDD_HHMMSS timestamp.
+/// Dates are computed with the days to civil algorithm, to avoid pulling a time dependency.
+pub(crate) fn format_timestamp(secs: u64) -> String {
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+    let z: i64 = days + 719468;
+    let era: i64 = z.div_euclid(146097);
+    let doe: i64 = z.rem_euclid(146097);
+    let yoe: i64 = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy: i64 = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp: i64 = (5 * doy + 2) / 153;
+    let day: i64 = doy - (153 * mp + 2) / 5 + 1;
+    let month: i64 = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year: i64 = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}_{:02}{:02}{:02}",
+            year, month, day, secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+/// Gets the sequential name of the file from its base name and the current count.
+/// Note that the base_name will already have the trailing "_", so there is no need to add it.
+///
+/// \param base_name: base name with the trailing '_', e.g. "test_".
+/// \param cnt: counter of the name, ignored in SequentialMode::Timestamp.
+/// \param config: file manager configuration, for the mode, separator, padding and extension.
+/// \return: the sequential name, e.g. "test_004.map".
+///
+pub fn get_sequential_name_from_count(base_name: &str, cnt: u16, config: &FileManagerConfig) -> String {
+    // Without padding the width is 0, so the counter takes only its digits.
+    let width: usize = if config.sequential_padding { config.sequential_padding_len } else { 0 };
+    match config.sequential_mode {
+        SequentialMode::Counter =>
+            format!("{}{:0>width$}.{}", base_name, cnt, config.extension),
+        SequentialMode::Timestamp =>
+            format!("{}{}.{}", base_name, current_timestamp(config), config.extension),
+        SequentialMode::CounterAndTimestamp =>
+            format!("{}{:0>width$}{}{}.{}", base_name, cnt, config.sequential_separator,
+                    current_timestamp(config), config.extension),
+    }
+}
+
+/// Parses a sequential counter, zero padded to the padding length if sequential_padding is set,
+/// or without leading zeros otherwise.
+fn parse_sequential_counter(cnt: &str, config: &FileManagerConfig) -> Option<u16> {
+    let valid_len: bool = if config.sequential_padding {
+        cnt.len() == config.sequential_padding_len
+    } else {
+        !cnt.is_empty() && (cnt == "0" || !cnt.starts_with('0'))
+    };
+    if valid_len && cnt.chars().all(|c| c.is_ascii_digit()) {
+        cnt.parse::<u16>().ok()
+    } else {
+        None
+    }
+}
+
+/// Gets the length of the counter at the end of a stem: the padding length, or the trailing
+/// digits if sequential_padding is not set.
+fn get_counter_len(stem: &str, config: &FileManagerConfig) -> usize {
+    if config.sequential_padding {
+        config.sequential_padding_len
+    } else {
+        stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len()
+    }
+}
+
+/// Checks if the string is a YYYYMMDD_HHMMSS timestamp.
+fn is_timestamp(timestamp: &str) -> bool {
+    timestamp.len() == TIMESTAMP_LEN && timestamp
+        .char_indices()
+        .all(|(i, c)| if i == 8 { c == '_' } else { c.is_ascii_digit() })
+}
+
+/// Parses the sequential suffix of a name, i.e. what comes after the base name and before the
+/// extension.
+///
+/// Returns: the (counter, timestamp) pair, with 0 or "" for the parts not used by the mode; None
+///          if the suffix does not match the mode.
+fn parse_sequential_suffix<'a>(suffix: &'a str, config: &FileManagerConfig)
+    -> Option<(u16, &'a str)> {
+    match config.sequential_mode {
+        SequentialMode::Counter => Some((parse_sequential_counter(suffix, config)?, "")),
+        SequentialMode::Timestamp => is_timestamp(suffix).then_some((0, suffix)),
+        SequentialMode::CounterAndTimestamp => {
+            let (cnt, timestamp) = suffix.split_once(config.sequential_separator)?;
+            if is_timestamp(timestamp) {
+                Some((parse_sequential_counter(cnt, config)?, timestamp))
+            } else {
+                None
+            }
+        },
+    }
+}
+
+/// Strips the base name from the beginning of a file name of its series. The case is ignored if
+/// case_insensitive_series is set.
+fn strip_base_name<'a>(name: &'a str, base_name: &str, config: &FileManagerConfig) -> Option<&'a str> {
+    if config.case_insensitive_series {
+        let prefix: &str = name.get(..base_name.len())?;
+        (prefix.to_lowercase() == base_name.to_lowercase()).then(|| &name[base_name.len()..])
+    } else {
+        name.strip_prefix(base_name)
+    }
+}
+
+/// Gets the files of a series with their (counter, timestamp) keys, in the order of the list.
+pub(crate) fn get_series<'a>(files: &'a [String], base_name: &str, config: &FileManagerConfig)
+    -> Vec<((u16, &'a str), &'a String)> {
+    files
+        .iter()
+        .filter_map(|name| {
+            let suffix: &str = strip_base_name(name, base_name, config)?
+                .strip_suffix(config.extension.as_str())?
+                .strip_suffix('.')?;
+            Some((parse_sequential_suffix(suffix, config)?, name))
+        })
+        .collect()
+}
+
+/// Searches the files to get the next sequential name.
+/// if next is true the next unused name is returned; otherwise the last used name.
+/// If the next counter is over the max and reuse_free_counters is set, the lowest free counter is
+/// used instead, so it only fails when the whole range is in use.
+///
+/// \param dir: directory of the series.
+/// \param base_name: base name with the trailing '_', e.g. "test_".
+/// \param next: if true gets the next unused name; otherwise the last used one.
+/// \param config: file manager configuration.
+/// \return: the sequential name, or Error::InvalidSequentialName if the counters are exhausted.
+///
+pub fn get_sequential_name(dir: &Path, base_name: &str, next: bool, config: &FileManagerConfig)
+    -> Result<String> {
+    get_sequential_name_and_count(dir, base_name, next, config).map(|(name, _)| name)
+}
+
+/// Same as get_sequential_name, also returning the counter of the name, e.g. 7 for "test_007.map".
+///
+/// \param dir: directory of the series.
+/// \param base_name: base name with the trailing '_', e.g. "test_".
+/// \param next: if true gets the next unused name; otherwise the last used one.
+/// \param config: file manager configuration.
+/// \return: the sequential name and its counter.
+///
+pub fn get_sequential_name_and_count(dir: &Path, base_name: &str, next: bool,
+                                     config: &FileManagerConfig) -> Result<(String, u16)> {
+    let file_list: Vec<String> = get_file_list(dir, config)?;
+    let series: Vec<((u16, &str), &String)> = get_series(&file_list, base_name, config);
+    // Last used name of the series, the counter takes precedence over the timestamp.
+    let last = series.iter().max_by_key(|(key, _)| *key);
+
+    let mut cnt: u16 = match last {
+        Some(((cnt, _), name)) if !next => return Ok((name.to_string(), *cnt)),
+        Some(((cnt, _), _)) => cnt.saturating_add(1),
+        None => 0,
+    };
+    if cnt > config.sequential_max_number && config.reuse_free_counters {
+        if let Some(free) = (0..=config.sequential_max_number)
+            .find(|free| !series.iter().any(|((cnt, _), _)| cnt == free)) {
+            cnt = free;
+        }
+    }
+    if cnt <= config.sequential_max_number {
+        Ok((get_sequential_name_from_count(base_name, cnt, config), cnt))
+    } else {
+        Err(Error::InvalidSequentialName(config.sequential_max_number))
+    }
+}
+
+/// Gets the next sequential name and claims it, creating the file empty so that another process
+/// cannot take the same name. If the name has been taken since it was computed, the next counters
+/// are tried until one can be created. The claimed file is then overwritten with its content.
+///
+/// \param dir: directory of the series.
+/// \param base_name: base name with the trailing '_', e.g. "test_".
+/// \param config: file manager configuration.
+/// \return: the claimed name and its counter, or Error::InvalidSequentialName if the counters are
+///          exhausted. In SequentialMode::Timestamp there is no counter to try, so a taken name
+///          fails right away with Error::FileExists.
+///
+pub fn claim_sequential_name(dir: &Path, base_name: &str, config: &FileManagerConfig)
+    -> Result<(String, u16)> {
+    let (mut name, mut cnt): (String, u16) =
+        get_sequential_name_and_count(dir, base_name, true, config)?;
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(dir.join(&name)) {
+            Ok(_) => return Ok((name, cnt)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {},
+            Err(e) => return Err(e.into()),
+        }
+        if config.sequential_mode == SequentialMode::Timestamp {
+            // The name does not depend on the counter, the next ones would be the same.
+            return Err(Error::FileExists(dir.join(&name)));
+        }
+        if cnt >= config.sequential_max_number {
+            return Err(Error::InvalidSequentialName(config.sequential_max_number));
+        }
+        cnt += 1;
+        name = get_sequential_name_from_count(base_name, cnt, config);
+    }
+}
+
+/// Reserves the next sequential path of a series, creating the file empty (see
+/// claim_sequential_name), so that a later save to it cannot collide with another process, e.g.
+/// when a loaded map is edited and saved as a new version. The file has the configured extension.
+///
+/// \param dir: directory of the series.
+/// \param base_name: base name, with or without the trailing '_', e.g. "test_".
+/// \param config: file manager configuration.
+/// \return: the path of the reserved file, e.g. "dir/test_004.map".
+///
+pub fn reserve_next_name(dir: &Path, base_name: &str, config: &FileManagerConfig)
+    -> Result<PathBuf> {
+    let separator: char = config.sequential_separator;
+    let base_name: String = format!("{}{}", base_name.trim_end_matches(separator), separator);
+    let (name, _): (String, u16) = claim_sequential_name(dir, &base_name, config)?;
+    Ok(dir.join(name))
+}
+
+/// Gets the next unused sequential name for a file name, e.g. "test.map" -> "test_004.map".
+///
+/// \param dir: directory of the series.
+/// \param file_name: file name with extension.
+/// \param config: file manager configuration.
+/// \return: the next sequential name, Error::UnknownFileType if the name has no extension.
+///
+pub fn get_next_sequential_name(dir: &Path, file_name: &str, config: &FileManagerConfig)
+    -> Result<String> {
+    let (base_name, _) = file_name.split_once('.')
+        .ok_or_else(|| Error::UnknownFileType(config.extension.clone()))?;
+    let base_name = format!("{}{}", base_name, config.sequential_separator);
+    get_sequential_name(dir, &base_name, true, config)
+}
+
+/// Gets the counter of a sequential name, e.g. 7 for "test_007.map", or 0 in the Timestamp mode.
+/// None if the name is not sequential.
+pub(crate) fn get_sequential_counter(file_name: &str, config: &FileManagerConfig) -> Option<u16> {
+    let base_name: String = is_sequential_name(file_name, config);
+    let (stem, _) = file_name.split_once('.')?;
+    let suffix: &str = stem.strip_prefix(base_name.as_str()).filter(|_| base_name != file_name)?;
+    parse_sequential_suffix(suffix, config).map(|(cnt, _)| cnt)
+}
+
+/// Gets the base name of a typed sequential name (ending in '_'), removing the sequential suffix
+/// it may already have, e.g. "base_007_" -> "base_"; while "base_" is kept as is.
+pub(crate) fn get_sequential_base_name(typed: &str, config: &FileManagerConfig) -> String {
+    if let Some(name) = typed.strip_suffix(config.sequential_separator) {
+        let file_name: String = format!("{}.{}", name, config.extension);
+        let base_name: String = is_sequential_name(&file_name, config);
+        if base_name != file_name {
+            return base_name;
+        }
+    }
+    typed.to_string()
+}
+
+/// If the name is sequential, return basename only.
+/// Sequential names end in <base_name>_<suffix>.<extension>, where the suffix depends on the
+/// sequential mode (e.g. XXX for counters), and '_' is the configured sequential_separator.
+///
+/// \param file_name: file name with extension.
+/// \param config: file manager configuration.
+/// \return: the base name with the trailing '_' (e.g. "test_004.map" -> "test_"), or the file
+///          name unchanged if it is not sequential.
+///
+pub fn is_sequential_name(file_name: &str, config: &FileManagerConfig) -> String {
+    let Some((stem, _)) = file_name.split_once('.') else {
+        return file_name.to_string(); // Without extension it cannot be a sequential name.
+    };
+    let separator_len: usize = config.sequential_separator.len_utf8();
+    let suffix_len: Option<usize> = match config.sequential_mode {
+        SequentialMode::Counter => Some(get_counter_len(stem, config)),
+        SequentialMode::Timestamp => Some(TIMESTAMP_LEN),
+        SequentialMode::CounterAndTimestamp => stem
+            .len()
+            .checked_sub(separator_len + TIMESTAMP_LEN)
+            .and_then(|end| stem.get(0..end))
+            .map(|cnt| get_counter_len(cnt, config) + separator_len + TIMESTAMP_LEN),
+    };
+    let base_name: Option<&str> = suffix_len
+        .and_then(|suffix_len| stem.len().checked_sub(suffix_len))
+        .and_then(|end| stem.get(0..end));
+    if let Some(base_name) = base_name {
+        if base_name.ends_with(config.sequential_separator) &&
+            parse_sequential_suffix(&stem[base_name.len()..], config).is_some() {
+            return base_name.to_string();
+        }
+    }
+    file_name.to_string()
+}
+
+/// Gets the path of the next version of a file in the same directory, e.g. "base_003.map" ->
+/// "base_004.map" (or the next unused counter of the series). A non sequential name is the first
+/// version of its series, e.g. "base.map" -> "base_001.map".
+///
+/// \param current: path of the current version.
+/// \param config: file manager configuration.
+/// \return: the path of the next version, Error::UnknownFileType if the name has no extension.
+///
+pub fn next_version_path(current: &Path, config: &FileManagerConfig) -> Result<PathBuf> {
+    let dir: &Path = match current.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name: String = current
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| Error::UnknownFileType(config.extension.clone()))?;
+    let Some((stem, _)) = file_name.split_once('.') else {
+        return Err(Error::UnknownFileType(config.extension.clone()));
+    };
+    let base_name: String = is_sequential_name(&file_name, config);
+    let is_first_version: bool = base_name == file_name;
+    let base_name: String = if is_first_version {
+        format!("{}{}", stem, config.sequential_separator)
+    } else {
+        base_name
+    };
+    let mut next_name: String = get_sequential_name(dir, &base_name, true, config)?;
+    // The non sequential name takes the place of the first counter.
+    if is_first_version && next_name == get_sequential_name_from_count(&base_name, 0, config) {
+        next_name = get_sequential_name_from_count(&base_name, 1, config);
+    }
+    Ok(dir.join(next_name))
+}
+
+/// Gets the latest file of each sequential series of the dir (the one with the highest counter,
+/// or the newest timestamp), e.g. "worldA_020.map" and "worldB_005.map". Files that are not part
+/// of a series are kept as they are.
+///
+/// \param dir: directory of the files.
+/// \param config: file manager configuration, for the extension and sequential mode.
+/// \return: the latest file of each series and the single files, in listing order.
+///
+pub fn latest_per_series(dir: &Path, config: &FileManagerConfig) -> Result<Vec<String>> {
+    Ok(get_latest_per_series(&get_file_list(dir, config)?, config))
+}
+
+/// Gets the base names of the sequential series of the dir, e.g. "worldA_" and "worldB_" for
+/// "worldA_001.map", "worldA_002.map", "worldB_005.map" and "notes.map". Files that are not part of
+/// a series are left out.
+///
+/// \param dir: directory of the files.
+/// \param config: file manager configuration, for the extension and sequential mode.
+/// \return: the base names with the trailing '_', without duplicates, in listing order.
+///
+pub fn list_series_bases(dir: &Path, config: &FileManagerConfig) -> Result<Vec<String>> {
+    let mut bases: Vec<String> = Vec::new();
+    for file in get_file_list(dir, config)?.iter() {
+        let base_name: String = is_sequential_name(file, config);
+        if base_name != *file && !bases.contains(&base_name) {
+            bases.push(base_name);
+        }
+    }
+    Ok(bases)
+}
+
+/// Gets the counters missing in a series, between 1 and the highest counter found, e.g. 3 and 5
+/// for "test_001.map", "test_002.map", "test_004.map" and "test_006.map". A gap usually means a
+/// lost or deleted file.
+///
+/// \param dir: directory of the series.
+/// \param base_name: base name with the trailing '_', e.g. "test_".
+/// \param config: file manager configuration, for the extension and sequential mode.
+/// \return: the missing counters in ascending order, empty if there are no gaps.
+///
+pub fn audit_series(dir: &Path, base_name: &str, config: &FileManagerConfig) -> Result<Vec<u16>> {
+    let file_list: Vec<String> = get_file_list(dir, config)?;
+    Ok(get_missing_counters(&get_series(&file_list, base_name, config)))
+}
+
+/// Gets the lowest and highest counters of a series, e.g. to show "versions 1-37".
+///
+/// \param dir: directory of the series.
+/// \param base_name: base name with the trailing '_', e.g. "test_".
+/// \param config: file manager configuration, for the extension and sequential mode.
+/// \return: the (min, max) counters, None if the series has no files. In
+///          SequentialMode::Timestamp the names have no counters, so both are 0.
+///
+pub fn series_bounds(dir: &Path, base_name: &str, config: &FileManagerConfig)
+    -> Result<Option<(u16, u16)>> {
+    let file_list: Vec<String> = get_file_list(dir, config)?;
+    let series: Vec<((u16, &str), &String)> = get_series(&file_list, base_name, config);
+    let min: Option<u16> = series.iter().map(|((cnt, _), _)| *cnt).min();
+    let max: Option<u16> = series.iter().map(|((cnt, _), _)| *cnt).max();
+    Ok(min.zip(max))
+}
+
+/// Gets the counters missing between 1 and the highest counter of the series.
+/// Counter 0 is not expected, as series started from an existing file begin at 1.
+pub(crate) fn get_missing_counters(series: &[((u16, &str), &String)]) -> Vec<u16> {
+    let max: u16 = series.iter().map(|((cnt, _), _)| *cnt).max().unwrap_or(0);
+    (1..=max).filter(|expected| !series.iter().any(|((cnt, _), _)| cnt == expected)).collect()
+}
+
+/// Gets the renames that make the counters of a series contiguous from 1, in the order of the
+/// current counters. The timestamps of SequentialMode::CounterAndTimestamp are kept, and the
+/// files already in place are not renamed.
+pub(crate) fn get_renumber_renames(series: &[((u16, &str), &String)], base_name: &str,
+                                   config: &FileManagerConfig) -> Vec<(String, String)> {
+    let mut sorted: Vec<((u16, &str), &String)> = series.to_vec();
+    sorted.sort_by_key(|(key, _)| *key);
+    sorted
+        .iter()
+        .enumerate()
+        .filter_map(|(i, ((_, timestamp), name))| {
+            // The base of the file is used, as its case may differ with case_insensitive_series.
+            let mut new_name: String = get_sequential_name_from_count(&name[..base_name.len()],
+                                                                      (i + 1) as u16, config);
+            if !timestamp.is_empty() {
+                let end: usize = new_name.len() - config.extension.len() - 1;
+                new_name.replace_range(end - TIMESTAMP_LEN..end, timestamp);
+            }
+            (new_name != **name).then(|| ((*name).clone(), new_name))
+        })
+        .collect()
+}
+
+/// Keeps the latest file of each series of the list, in the position of its first file.
+pub(crate) fn get_latest_per_series(files: &[String], config: &FileManagerConfig) -> Vec<String> {
+    let mut latest: Vec<(String, (u16, &str), &String)> = Vec::new();
+    for file in files.iter() {
+        let base_name: String = is_sequential_name(file, config);
+        let key: (u16, &str) = file
+            .strip_prefix(base_name.as_str())
+            .and_then(|suffix| suffix.strip_suffix(config.extension.as_str()))
+            .and_then(|suffix| suffix.strip_suffix('.'))
+            .and_then(|suffix| parse_sequential_suffix(suffix, config))
+            .unwrap_or((0, "")); // Single files are their own series.
+        match latest.iter_mut().find(|(base, _, _)| *base == base_name) {
+            Some(series) if key > series.1 => *series = (base_name, key, file),
+            Some(_) => (),
+            None => latest.push((base_name, key, file)),
+        }
+    }
+    latest.into_iter().map(|(_, _, file)| file.clone()).collect()
+}
+
+// --------------------------------------------------------------------------------
+// Tests
+// --------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::tests::{test_dir, touch};
+    use crate::FixedClock;
+
+    /// 2024-01-31 23:59:59 UTC.
+    const NOW: u64 = 1_706_745_599;
+
+    fn timestamp_config(mode: SequentialMode) -> FileManagerConfig {
+        FileManagerConfig::builder()
+            .sequential_mode(mode)
+            .clock(FixedClock(SystemTime::UNIX_EPOCH + Duration::from_secs(NOW)))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn timestamps_are_formatted_in_utc() {
+        assert_eq!(format_timestamp(0), "19700101_000000");
+        assert_eq!(format_timestamp(NOW), "20240131_235959");
+        assert_eq!(format_timestamp(NOW + 1), "20240201_000000");
+        assert_eq!(format_timestamp(1_709_208_000), "20240229_120000"); // Leap day.
+        assert_eq!(format_timestamp(951_825_600), "20000229_120000"); // Leap century.
+    }
+
+    #[test]
+    fn timestamp_names_use_the_configured_clock() {
+        let config: FileManagerConfig = timestamp_config(SequentialMode::Timestamp);
+        assert_eq!(get_sequential_name_from_count("test_", 4, &config),
+                   "test_20240131_235959.map");
+        let config: FileManagerConfig = timestamp_config(SequentialMode::CounterAndTimestamp);
+        assert_eq!(get_sequential_name_from_count("test_", 4, &config),
+                   "test_004_20240131_235959.map");
+    }
+
+    #[test]
+    fn timestamp_names_are_sequential() {
+        let config: FileManagerConfig = timestamp_config(SequentialMode::Timestamp);
+        assert_eq!(is_sequential_name("test_20240131_235959.map", &config), "test_");
+        assert_eq!(is_sequential_name("test_20240131_2359.map", &config),
+                   "test_20240131_2359.map");
+        let config: FileManagerConfig = timestamp_config(SequentialMode::CounterAndTimestamp);
+        assert_eq!(is_sequential_name("test_004_20240131_235959.map", &config), "test_");
+    }
+
+    #[test]
+    fn timestamp_series_is_ordered_by_time() {
+        let dir: PathBuf = test_dir("timestamp_series");
+        touch(&dir, &["test_20231231_120000.map", "test_20240101_080000.map", "other.map"]);
+        let config: FileManagerConfig = timestamp_config(SequentialMode::Timestamp);
+        assert_eq!(get_sequential_name(&dir, "test_", false, &config).unwrap(),
+                   "test_20240101_080000.map");
+        assert_eq!(get_sequential_name(&dir, "test_", true, &config).unwrap(),
+                   "test_20240131_235959.map");
+
+        let config: FileManagerConfig = timestamp_config(SequentialMode::CounterAndTimestamp);
+        touch(&dir, &["run_001_20240101_080000.map", "run_002_20231231_120000.map"]);
+        // The counter takes precedence over the timestamp.
+        assert_eq!(get_sequential_name(&dir, "run_", false, &config).unwrap(),
+                   "run_002_20231231_120000.map");
+        assert_eq!(get_sequential_name(&dir, "run_", true, &config).unwrap(),
+                   "run_003_20240131_235959.map");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn series_case_is_matched_as_configured() {
+        let dir: PathBuf = test_dir("series_case");
+        touch(&dir, &["Base_005.map", "base_002.map"]);
+        let config: FileManagerConfig = FileManagerConfig::default();
+        assert_eq!(get_sequential_name(&dir, "base_", true, &config).unwrap(), "base_003.map");
+        assert_eq!(get_sequential_name(&dir, "Base_", true, &config).unwrap(), "Base_006.map");
+
+        let config: FileManagerConfig =
+            FileManagerConfig::builder().case_insensitive_series(true).build().unwrap();
+        assert_eq!(get_sequential_name(&dir, "base_", true, &config).unwrap(), "base_006.map");
+        assert_eq!(get_sequential_name(&dir, "BASE_", false, &config).unwrap(), "Base_005.map");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn claimed_names_skip_the_taken_counters() {
+        let dir: PathBuf = test_dir("claim_taken");
+        touch(&dir, &["test_000.map", "test_001.map"]);
+        // Not listed as part of the series, as a file created after the listing would not be.
+        fs::create_dir(dir.join("test_002.map")).unwrap();
+        let config: FileManagerConfig = FileManagerConfig::default();
+        assert_eq!(get_sequential_name(&dir, "test_", true, &config).unwrap(), "test_002.map");
+        assert_eq!(claim_sequential_name(&dir, "test_", &config).unwrap(),
+                   ("test_003.map".to_string(), 3));
+        assert!(dir.join("test_003.map").is_file());
+        assert_eq!(claim_sequential_name(&dir, "test_", &config).unwrap(),
+                   ("test_004.map".to_string(), 4));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn taken_timestamp_names_are_not_retried() {
+        let dir: PathBuf = test_dir("claim_taken_timestamp");
+        let config: FileManagerConfig = timestamp_config(SequentialMode::Timestamp);
+        assert_eq!(claim_sequential_name(&dir, "test_", &config).unwrap().0,
+                   "test_20240131_235959.map");
+        // Same clock time, so the same name: there is no other one to try.
+        match claim_sequential_name(&dir, "test_", &config) {
+            Err(Error::FileExists(path)) => {
+                assert_eq!(path, dir.join("test_20240131_235959.map"))
+            },
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(matches!(reserve_next_name(&dir, "test", &config), Err(Error::FileExists(_))));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unpadded_hyphen_series_next_to_padded_underscore_ones() {
+        let dir: PathBuf = test_dir("separator_series");
+        touch(&dir, &["test-4.map", "test-12.map", "test_001.map", "test_002.map"]);
+        let config: FileManagerConfig = FileManagerConfig::builder()
+            .sequential_separator('-')
+            .sequential_padding(false)
+            .build()
+            .unwrap();
+        // The counters are compared as numbers, not as text.
+        assert_eq!(get_sequential_name(&dir, "test-", false, &config).unwrap(), "test-12.map");
+        assert_eq!(get_sequential_name(&dir, "test-", true, &config).unwrap(), "test-13.map");
+        assert_eq!(get_sequential_name(&dir, "new-", true, &config).unwrap(), "new-0.map");
+
+        let config: FileManagerConfig = FileManagerConfig::default();
+        assert_eq!(get_sequential_name(&dir, "test_", false, &config).unwrap(), "test_002.map");
+        assert_eq!(get_sequential_name(&dir, "test_", true, &config).unwrap(), "test_003.map");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}